@@ -44,7 +44,10 @@ class TimelineEngine {
         
         clip.id.withCString { idPtr in
             clip.url.withCString { urlPtr in
-                engine_add_clip(ptr, idPtr, urlPtr, clip.inPoint, clip.outPoint, UInt(index))
+                let status = engine_add_clip(ptr, idPtr, urlPtr, clip.inPoint, clip.outPoint, UInt(index))
+                if status == 1 {
+                    print("TimelineEngine: Index \(index) out of bounds, clip was appended to the end")
+                }
             }
         }
     }
@@ -113,9 +116,11 @@ class TimelineEngine {
         
         print("TimelineEngine: Cutting clip at index \(index) at position \(position)")
         
-        engine_cut_clip(ptr, UInt(index), position)
+        guard engine_cut_clip(ptr, UInt(index), position) else {
+            print("TimelineEngine: Cut rejected - one half would be shorter than the minimum clip duration")
+            return
+        }
         
-        // Verify the cut worked by checking if we now have one more clip
         let newCount = getClipCount()
         print("TimelineEngine: After cut, clip count changed from \(count) to \(newCount)")
     }
@@ -11,62 +11,878 @@
 #include <stdint.h>
 #include <stdlib.h>
 
+/**
+ * Index of the primary magnetic video track. Every timeline has one.
+ */
+#define MAIN_TRACK 0
+
+/**
+ * Version of the saved project format written by this build. Bump it whenever the format
+ * changes and add the upgrade step to `migrate_project_json`.
+ */
+#define PROJECT_SCHEMA_VERSION 2
+
+typedef enum EngineEventType {
+  EngineEventType_ClipInserted,
+  EngineEventType_ClipRemoved,
+  EngineEventType_ClipUpdated,
+  EngineEventType_PlaybackChanged,
+  EngineEventType_TimelineChanged,
+  EngineEventType_ProjectChanged,
+} EngineEventType;
+
+typedef enum TrackKind {
+  TrackKind_Video,
+  TrackKind_Audio,
+  TrackKind_Overlay,
+} TrackKind;
+
+typedef enum TransitionKind {
+  TransitionKind_CrossDissolve,
+} TransitionKind;
+
 typedef struct Engine Engine;
 
+/**
+ * Placement of a clip on the canvas. Stored for the compositor; the engine doesn't apply it.
+ */
+typedef struct Transform {
+  float x;
+  float y;
+  float scale;
+  float rotation_deg;
+} Transform;
+
+/**
+ * Called with an `EngineEventType` and a JSON payload that is only valid for the duration of the call.
+ */
+typedef void (*EngineEventCallback)(int32_t event_type, const char *payload_json, void *user_data);
+
+/**
+ * Called with the new value whenever the project goes from saved to unsaved or back.
+ */
+typedef void (*DirtyCallback)(bool is_dirty, void *user_data);
+
 typedef struct PlaybackClipInfo {
   char *id;
   char *url;
   uint64_t time_in_clip_ms;
+  bool is_offline;
+  bool is_enabled;
 } PlaybackClipInfo;
 
+typedef struct TransitionInfo {
+  char *from_id;
+  char *from_url;
+  uint64_t from_time_ms;
+  char *to_id;
+  char *to_url;
+  uint64_t to_time_ms;
+  float progress;
+  enum TransitionKind kind;
+} TransitionInfo;
+
 struct Engine *engine_new(void);
 
 void engine_free(struct Engine *engine);
 
-void engine_add_clip(struct Engine *engine,
-                     const char *id,
-                     const char *url,
-                     uint64_t in_ms,
-                     uint64_t out_ms,
-                     uintptr_t idx);
+/**
+ * Inserts a clip at `idx` on the main track. Returns 0 if it landed at `idx`, 1 if `idx` was past
+ * the end and the clip was appended instead, or -1 if a pointer is null or the clip has an
+ * empty url or `out_ms <= in_ms`.
+ */
+int32_t engine_add_clip(struct Engine *engine,
+                        const char *id,
+                        const char *url,
+                        uint64_t in_ms,
+                        uint64_t out_ms,
+                        uintptr_t idx);
+
+/**
+ * Inserts a still image at `idx` on the main track, lasting the project's default still
+ * duration. Returns the same codes as `engine_add_clip`.
+ */
+int32_t engine_add_still(struct Engine *engine, const char *id, const char *url, uintptr_t idx);
+
+/**
+ * `engine_add_clip` for callers that only need to know whether the clip was accepted.
+ */
+bool engine_try_add_clip(struct Engine *engine,
+                         const char *id,
+                         const char *url,
+                         uint64_t in_ms,
+                         uint64_t out_ms,
+                         uintptr_t idx);
+
+/**
+ * Updates the url and range of the main-track clip with this id in place, or appends a new clip
+ * if there is none. A new url also renames the clip after its file. Returns true for an update,
+ * false for an insert. An empty url or
+ * `out_ms <= in_ms` changes nothing and returns false.
+ */
+bool engine_upsert_clip(struct Engine *engine,
+                        const char *id,
+                        const char *url,
+                        uint64_t in_ms,
+                        uint64_t out_ms);
+
+/**
+ * Adds a clip to the end of the main track. Returns the index it was appended at, or -1 for a
+ * null pointer or a clip with an empty url or `out_ms <= in_ms`.
+ */
+int64_t engine_append_clip(struct Engine *engine,
+                           const char *id,
+                           const char *url,
+                           uint64_t in_ms,
+                           uint64_t out_ms);
+
+/**
+ * Same as `engine_add_clip` but targets any track. Returns -1 if the track doesn't exist or the
+ * clip is rejected.
+ */
+int32_t engine_add_clip_to_track(struct Engine *engine,
+                                 uintptr_t track,
+                                 const char *id,
+                                 const char *url,
+                                 uint64_t in_ms,
+                                 uint64_t out_ms,
+                                 uintptr_t idx);
+
+/**
+ * Inserts every file from a scanner manifest (`[{"url": ..., "duration_ms": ...}]`) at
+ * `start_idx` as full-length clips, in one undoable edit. Entries without a url or a duration are
+ * skipped and counted in `out_skipped` if it's non-null. Returns the number added, or -1 for bad JSON.
+ */
+int64_t engine_import_clips_from_manifest(struct Engine *engine,
+                                          const char *json_data,
+                                          uintptr_t start_idx,
+                                          uintptr_t *out_skipped);
+
+/**
+ * Inserts a new clip at timeline position `time_ms` on the main track, splitting the clip there
+ * if the time falls inside it. Times past the end append. An empty url or `out_ms <= in_ms`
+ * changes nothing.
+ */
+void engine_insert_clip_at_time(struct Engine *engine,
+                                const char *id,
+                                const char *url,
+                                uint64_t in_ms,
+                                uint64_t out_ms,
+                                uint64_t time_ms);
+
+/**
+ * Places a new clip at `time_ms` on the main track, replacing the content it covers. Clips
+ * partly under it are trimmed and nothing downstream moves. An empty url or `out_ms <= in_ms`
+ * changes nothing.
+ */
+void engine_overwrite_clip_at_time(struct Engine *engine,
+                                   const char *id,
+                                   const char *url,
+                                   uint64_t in_ms,
+                                   uint64_t out_ms,
+                                   uint64_t time_ms);
+
+/**
+ * Opens `duration_ms` of empty space at `time_ms` on the main track, splitting the clip there if
+ * needed. Everything after moves later; the space is a gap, so `engine_close_gaps` removes it.
+ */
+void engine_ripple_space(struct Engine *engine, uint64_t time_ms, uint64_t duration_ms);
 
 void engine_remove_clip(struct Engine *engine, uintptr_t idx);
 
-void engine_cut_clip(struct Engine *engine, uintptr_t idx, uint64_t position);
+/**
+ * Splits the clip at `idx` at source time `position`. Returns false if the cut was rejected,
+ * e.g. because one half would be shorter than the minimum clip duration.
+ */
+bool engine_cut_clip(struct Engine *engine, uintptr_t idx, uint64_t position);
+
+/**
+ * Sets the shortest clip a cut may leave behind. Pass 0 to use one frame at the project frame rate.
+ */
+void engine_set_min_clip_duration(struct Engine *engine, uint64_t duration_ms);
+
+/**
+ * Moves the in point of the clip at `idx`; later clips shift to follow. Returns false if
+ * `new_in` would invert the clip.
+ */
+bool engine_ripple_trim_start(struct Engine *engine, uintptr_t idx, uint64_t new_in);
+
+/**
+ * Moves the out point of the clip at `idx`; later clips shift to follow. Returns false if
+ * `new_out` would invert the clip.
+ */
+bool engine_ripple_trim_end(struct Engine *engine, uintptr_t idx, uint64_t new_out);
+
+/**
+ * When enabled, edits move the playhead so it stays on the same frame of the main-track clip
+ * it was over, e.g. removing an earlier clip pulls it back. If that clip is removed the
+ * playhead stays where it is.
+ */
+void engine_set_keep_playhead_on_content(struct Engine *engine, bool enabled);
+
+/**
+ * Ripple mode is on by default. Turning it off makes removes and shortening trims leave a gap
+ * behind, so later clips keep their positions. A trimmed head leaves its gap before the clip and
+ * a trimmed tail after it. Lengthening trims still push later clips out.
+ */
+void engine_set_ripple_mode(struct Engine *engine, bool enabled);
+
+/**
+ * When enabled, cut positions are rounded to the nearest frame boundary at the project frame rate.
+ */
+void engine_set_snap_to_frames(struct Engine *engine, bool enabled);
 
 void engine_update_clip_range(struct Engine *engine,
                               uintptr_t idx,
                               uint64_t in_point,
                               uint64_t out_point);
 
+/**
+ * Slip edit: shows a different part of the source by moving the clip's in and out points
+ * together by `delta_ms`, stopping at the ends of the media. The clip's place and length on the
+ * timeline don't change.
+ */
+void engine_slip_clip(struct Engine *engine, uintptr_t idx, int64_t delta_ms);
+
+/**
+ * Slide edit: moves the clip at `idx` `delta_ms` later (or earlier, if negative) by lengthening
+ * one neighbour and shortening the other, so the total duration stays the same. Does nothing
+ * for the first or last clip, or if a neighbour would end up empty or past its media.
+ */
+void engine_slide_clip(struct Engine *engine, uintptr_t idx, int64_t delta_ms);
+
+/**
+ * Moves the in point of the clip at `idx` by a signed delta. Clamped so the clip keeps at
+ * least 1 ms, rather than rejected, so a held key trims smoothly up to the limit.
+ */
+void engine_nudge_clip_in(struct Engine *engine, uintptr_t idx, int64_t delta_ms);
+
+/**
+ * Moves the out point of the clip at `idx` by a signed delta. Clamped to keep at least 1 ms
+ * and to stay within the source media when its length is known.
+ */
+void engine_nudge_clip_out(struct Engine *engine, uintptr_t idx, int64_t delta_ms);
+
+void engine_move_clip(struct Engine *engine, uintptr_t from, uintptr_t to);
+
+/**
+ * Copies the clip at `idx` to `idx + 1`. Returns the copy's index, or -1 if there is no such clip.
+ */
+int64_t engine_duplicate_clip(struct Engine *engine, uintptr_t idx);
+
+/**
+ * Copies the clip at `idx` to the clipboard. Returns false for an invalid index.
+ */
+bool engine_copy_clip(struct Engine *engine, uintptr_t idx);
+
+/**
+ * Pastes the clipboard clip at `idx` (appended if past the end). Returns false if the clipboard
+ * is empty or holds a gap.
+ */
+bool engine_paste_clip(struct Engine *engine, uintptr_t idx);
+
+bool engine_has_clipboard_content(const struct Engine *engine);
+
+/**
+ * Merges the clip at `idx` with the next one. Returns false if they aren't contiguous segments of the same media.
+ */
+bool engine_join_clips(struct Engine *engine,
+                       uintptr_t idx);
+
+/**
+ * Whether `engine_join_clips(idx)` would join anything: the clips at `idx` and `idx + 1` are
+ * contiguous segments of the same media.
+ */
+bool engine_can_join(const struct Engine *engine, uintptr_t idx);
+
+/**
+ * Joins every run of contiguous segments of the same media on the main track, as repeated
+ * `engine_join_clips` would. Returns the number of joins made.
+ */
+uintptr_t engine_consolidate(struct Engine *engine);
+
+/**
+ * Replaces the clip at `idx` with a gap of the same duration, leaving downstream clips in place.
+ */
+void engine_lift_clip(struct Engine *engine, uintptr_t idx);
+
+/**
+ * Number of gaps on the main track, e.g. left behind by lifts.
+ */
+uintptr_t engine_get_gap_count(const struct Engine *engine);
+
+/**
+ * Removes every gap from the main track so the remaining clips play back to back.
+ */
+void engine_close_gaps(struct Engine *engine);
+
+/**
+ * Reverses the order of the main-track clips. Ids, ranges and the total duration are kept.
+ */
+void engine_reverse_timeline(struct Engine *engine);
+
+/**
+ * Makes the clip at `idx` the only selected clip.
+ */
+void engine_select_clip(struct Engine *engine, uintptr_t idx);
+
+/**
+ * Adds the clip at `idx` to the selection, or removes it if it's already selected.
+ */
+void engine_toggle_select_clip(struct Engine *engine, uintptr_t idx);
+
+void engine_clear_selection(struct Engine *engine);
+
+uintptr_t engine_get_selected_count(const struct Engine *engine);
+
+/**
+ * Id of the `idx`th selected clip, or null for an invalid index. Free with `free_rust_string`.
+ */
+char *engine_get_selected_clip_id(const struct Engine *engine, uintptr_t idx);
+
+/**
+ * Points the clip at `idx` at different media, e.g. full-res files in place of proxies. Timing
+ * and every other field are kept. Returns false for an empty url or invalid index.
+ */
+bool engine_relink_clip(struct Engine *engine, uintptr_t idx, const char *new_url);
+
+/**
+ * Records whether the clip's media is missing. Swift sets this after probing the file.
+ */
+void engine_set_clip_offline(struct Engine *engine, uintptr_t idx, bool offline);
+
+/**
+ * Disables or re-enables the clip at `idx`. A disabled clip keeps its place and duration, and
+ * playback info reports it so the compositor can show black.
+ */
+void engine_set_clip_enabled(struct Engine *engine, uintptr_t idx, bool enabled);
+
+/**
+ * Turns the clip at `idx` into a freeze frame holding source time `frame_time_ms` for its
+ * current duration, or back into normal playback. `frame_time_ms` is ignored when unfreezing.
+ * The in and out points are kept, so unfreezing plays the clip's original range again.
+ */
+void engine_set_clip_freeze(struct Engine *engine,
+                            uintptr_t idx,
+                            bool freeze,
+                            uint64_t frame_time_ms);
+
+/**
+ * Number of main-track clips whose media is missing.
+ */
+uintptr_t engine_get_offline_clip_count(const struct Engine *engine);
+
+/**
+ * Main-track index of the `n`th offline clip, or -1 if there are fewer than `n + 1`.
+ */
+int64_t engine_get_offline_clip_index(const struct Engine *engine, uintptr_t n);
+
+/**
+ * Rewrites the start of every clip url matching `old_prefix`, e.g. after the media folder moved.
+ * Covers every track and the audio lane in one edit. Returns the number of clips changed.
+ */
+uintptr_t engine_relink_by_prefix(struct Engine *engine,
+                                  const char *old_prefix,
+                                  const char *new_prefix);
+
+/**
+ * Swaps the in and out points of every inverted clip. Returns how many were fixed.
+ */
+uintptr_t engine_normalize_ranges(struct Engine *engine);
+
+/**
+ * Razors every track at timeline time `time_ms`. Tracks with a boundary there, or no clip
+ * under it, are left alone.
+ */
+void engine_split_at_time(struct Engine *engine, uint64_t time_ms);
+
+/**
+ * Index of the main-track clip at timeline time `time_ms`, or -1 in a gap or past the end.
+ * A time on a boundary belongs to the clip starting there.
+ */
+int64_t engine_get_clip_index_at_time(const struct Engine *engine, uint64_t time_ms);
+
+/**
+ * Current index of the main-track clip with this id, or -1 if there is none.
+ */
+int64_t engine_get_clip_index_by_id(const struct Engine *engine, const char *id);
+
+/**
+ * Id of the main-track clip after the one with this id, or null at the end or for an
+ * unknown id. Free with `free_rust_string`.
+ */
+char *engine_get_next_clip_id(const struct Engine *engine, const char *id);
+
+/**
+ * Id of the main-track clip before the one with this id, or null at the start or for an
+ * unknown id. Free with `free_rust_string`.
+ */
+char *engine_get_prev_clip_id(const struct Engine *engine, const char *id);
+
+/**
+ * Empties the timeline but keeps the project's name, settings and file path, unlike
+ * `engine_new_project`. Playback stops at the start.
+ */
+void engine_clear_timeline(struct Engine *engine);
+
+/**
+ * Groups every selected clip so group moves and removes treat them as one. Returns the new
+ * group id, or null if fewer than two clips are selected. Free with `free_rust_string`.
+ */
+char *engine_group_selected(struct Engine *engine);
+
+/**
+ * Dissolves a group; its clips stay where they are.
+ */
+void engine_ungroup(struct Engine *engine, const char *group_id);
+
+/**
+ * Group id of the clip at `idx`, or null if it isn't grouped. Free with `free_rust_string`.
+ */
+char *engine_get_clip_group_id(const struct Engine *engine, uintptr_t idx);
+
+/**
+ * Moves the clip at `idx` together with the rest of its main-track group, keeping their
+ * order. The block lands at index `to` among the clips outside the group. An ungrouped clip
+ * moves on its own.
+ */
+void engine_move_group(struct Engine *engine, uintptr_t idx, uintptr_t to);
+
+/**
+ * Deletes the clip at `idx` and every clip in its group, on any track, as one undoable edit.
+ * Returns the number removed.
+ */
+uintptr_t engine_remove_group(struct Engine *engine, uintptr_t idx);
+
+/**
+ * Deletes every selected clip as one undoable edit. Returns the number removed.
+ */
+uintptr_t engine_remove_selected(struct Engine *engine);
+
 uintptr_t engine_get_clip_count(const struct Engine *engine);
 
 char *engine_get_clip_id(const struct Engine *engine, uintptr_t idx);
 
 char *engine_get_clip_url(const struct Engine *engine, uintptr_t idx);
 
+char *engine_get_clip_name(const struct Engine *engine, uintptr_t idx);
+
+void engine_set_clip_name(struct Engine *engine, uintptr_t idx, const char *name);
+
 uint64_t engine_get_clip_in_point(const struct Engine *engine, uintptr_t idx);
 
 uint64_t engine_get_clip_out_point(const struct Engine *engine, uintptr_t idx);
 
+/**
+ * Sets the linear gain of a clip, clamped to 0.0..=4.0.
+ */
+void engine_set_clip_volume(struct Engine *engine, uintptr_t idx, float volume);
+
+float engine_get_clip_volume(const struct Engine *engine, uintptr_t idx);
+
+/**
+ * Sets a clip's playback rate. Values <= 0 are ignored.
+ */
+void engine_set_clip_speed(struct Engine *engine, uintptr_t idx, float speed);
+
+/**
+ * Retimes the clip at `idx` so it lasts `target_ms` on the timeline, by setting its speed to
+ * source duration / `target_ms`. The in and out points stay. Returns false for a target of 0,
+ * an invalid index or an empty clip.
+ */
+bool engine_fit_clip_to_duration(struct Engine *engine, uintptr_t idx, uint64_t target_ms);
+
+float engine_get_clip_speed(const struct Engine *engine, uintptr_t idx);
+
+/**
+ * Records the probed length of the clip's media so in/out edits can't run past it. Pass 0 if unknown.
+ */
+void engine_set_clip_source_duration(struct Engine *engine,
+                                     uintptr_t idx,
+                                     uint64_t duration_ms);
+
+/**
+ * Sets the clip's position, scale and rotation. Returns false for an invalid index or a
+ * non-positive scale.
+ */
+bool engine_set_clip_transform(struct Engine *engine,
+                               uintptr_t idx,
+                               float x,
+                               float y,
+                               float scale,
+                               float rotation_deg);
+
+/**
+ * The clip's transform, or identity for an invalid index.
+ */
+struct Transform engine_get_clip_transform(const struct Engine *engine, uintptr_t idx);
+
+/**
+ * Tags the clip at `idx` with a packed RGBA label color. Pass 0 to clear it.
+ */
+void engine_set_clip_label(struct Engine *engine, uintptr_t idx, uint32_t color);
+
+/**
+ * The clip's label color, or 0 for no label or an invalid index.
+ */
+uint32_t engine_get_clip_label(const struct Engine *engine, uintptr_t idx);
+
+/**
+ * Indices of the main-track clips tagged with `color`, as a JSON array. The caller frees the
+ * string with `free_rust_string`.
+ */
+char *engine_get_clips_with_label(const struct Engine *engine, uint32_t color);
+
+/**
+ * Indices of the main-track clips whose url contains `substring`, ignoring case, as a JSON
+ * array. The caller frees the string with `free_rust_string`.
+ */
+char *engine_find_clips_by_url(const struct Engine *engine, const char *substring);
+
+/**
+ * Sets the source time used for the clip's thumbnail, clamped to its in and out points.
+ */
+void engine_set_clip_poster_time(struct Engine *engine, uintptr_t idx, uint64_t time_ms);
+
+/**
+ * Source time of the clip's thumbnail frame, or 0 for an invalid index.
+ */
+uint64_t engine_get_clip_poster_time(const struct Engine *engine, uintptr_t idx);
+
+/**
+ * Sets the clip's fade-in and fade-out lengths. Each is clamped to half the clip's duration.
+ */
+void engine_set_clip_fades(struct Engine *engine,
+                           uintptr_t idx,
+                           uint64_t fade_in_ms,
+                           uint64_t fade_out_ms);
+
+/**
+ * Opacity the compositor should apply `offset_ms` into the clip's span on the timeline, i.e. the
+ * playhead time minus the clip's start, or 1.0 for an invalid index. This is not the source
+ * time in `PlaybackClipInfo.time_in_clip_ms`, which differs once the clip is trimmed or retimed.
+ */
+float engine_get_clip_opacity_at(const struct Engine *engine, uintptr_t idx, uint64_t offset_ms);
+
+/**
+ * On-timeline duration of the clip (its speed applied), or 0 for an invalid index.
+ */
+uint64_t engine_get_clip_duration(const struct Engine *engine, uintptr_t idx);
+
+/**
+ * Total length of the timeline in ms, or 0 when there's no project.
+ */
+uint64_t engine_get_total_duration(const struct Engine *engine);
+
+/**
+ * Places an audio clip at an absolute timeline position. It plays alongside the video tracks.
+ * An empty url or `out_ms <= in_ms` changes nothing.
+ */
+void engine_add_audio_clip(struct Engine *engine,
+                           const char *id,
+                           const char *url,
+                           uint64_t in_ms,
+                           uint64_t out_ms,
+                           uint64_t start_ms);
+
+/**
+ * Appends an empty track on top of the stack. Returns its index, or -1 on failure.
+ */
+int64_t engine_add_track(struct Engine *engine, enum TrackKind kind);
+
+/**
+ * Removes a track and its clips. The main track can't be removed.
+ */
+bool engine_remove_track(struct Engine *engine, uintptr_t track);
+
+uintptr_t engine_get_track_count(const struct Engine *engine);
+
+enum TrackKind engine_get_track_kind(const struct Engine *engine, uintptr_t track);
+
+uintptr_t engine_get_track_clip_count(const struct Engine *engine, uintptr_t track);
+
+void engine_remove_clip_from_track(struct Engine *engine, uintptr_t track, uintptr_t idx);
+
+/**
+ * Registers a callback invoked after every change to the timeline or playback state.
+ * Pass a null callback to stop receiving events.
+ */
+void engine_set_event_callback(struct Engine *engine,
+                               EngineEventCallback callback,
+                               void *user_data);
+
+/**
+ * Adds a transition between the clip at `idx` and the next one. Returns false if either clip is
+ * missing or shorter than `duration_ms`, or the next clip has less than `duration_ms` of media
+ * before its in point.
+ */
+bool engine_add_transition(struct Engine *engine,
+                           uintptr_t idx,
+                           uint64_t duration_ms,
+                           enum TransitionKind kind);
+
+/**
+ * Registers a callback fired when the unsaved-changes state flips, for the window's modified
+ * indicator. Pass a null callback to unregister.
+ */
+void engine_set_dirty_callback(struct Engine *engine, DirtyCallback callback, void *user_data);
+
+/**
+ * Adds a marker. Returns false if a marker with the same id already exists.
+ */
+bool engine_add_marker(struct Engine *engine,
+                       const char *id,
+                       uint64_t time_ms,
+                       const char *label,
+                       uint32_t color);
+
+bool engine_remove_marker(struct Engine *engine, const char *id);
+
+bool engine_move_marker(struct Engine *engine, const char *id, uint64_t time_ms);
+
+uintptr_t engine_get_marker_count(const struct Engine *engine);
+
+/**
+ * Markers are indexed in time order.
+ */
+char *engine_get_marker_id(const struct Engine *engine, uintptr_t idx);
+
+char *engine_get_marker_label(const struct Engine *engine, uintptr_t idx);
+
+uint64_t engine_get_marker_time(const struct Engine *engine, uintptr_t idx);
+
+uint32_t engine_get_marker_color(const struct Engine *engine, uintptr_t idx);
+
+/**
+ * Time of the first marker after `time_ms`, or -1 if there is none.
+ */
+int64_t engine_get_next_marker_after(const struct Engine *engine, uint64_t time_ms);
+
+/**
+ * Previews the clip at `idx` on its own: playback time 0 maps to its in point and every other
+ * clip is ignored. Not saved. Returns false for an invalid index.
+ */
+bool engine_solo_clip(struct Engine *engine, uintptr_t idx);
+
+/**
+ * Ends solo preview; playback shows the whole timeline again.
+ */
+void engine_clear_solo(struct Engine *engine);
+
+bool engine_undo(struct Engine *engine);
+
+bool engine_redo(struct Engine *engine);
+
+/**
+ * Groups the following commands into one undo step, e.g. split + move + trim. Events are held
+ * back until `engine_commit_transaction`. Returns false if one is already open.
+ */
+bool engine_begin_transaction(struct Engine *engine);
+
+/**
+ * Ends the open transaction as a single undo step and emits one `TimelineChanged`.
+ */
+bool engine_commit_transaction(struct Engine *engine);
+
+/**
+ * Ends the open transaction by undoing everything it did.
+ */
+bool engine_rollback_transaction(struct Engine *engine);
+
+bool engine_can_undo(const struct Engine *engine);
+
+bool engine_can_redo(const struct Engine *engine);
+
 void engine_play(struct Engine *engine);
 
 void engine_pause(struct Engine *engine);
 
+/**
+ * Pauses and rewinds to the start, unlike `engine_pause` which keeps the time.
+ */
+void engine_stop(struct Engine *engine);
+
 void engine_seek(struct Engine *engine, uint64_t time_ms);
 
+/**
+ * Seeks to a typed `HH:MM:SS:FF` timecode in the project frame rate, clamped to the end of the
+ * timeline. Returns false if the timecode can't be parsed.
+ */
+bool engine_seek_to_timecode(struct Engine *engine, const char *tc);
+
+/**
+ * The playhead position as an `HH:MM:SS:FF` timecode. Free with `free_rust_string`.
+ */
+char *engine_get_current_timecode(const struct Engine *engine);
+
+/**
+ * Seeks like `engine_seek` and returns where the playhead actually landed, which is clamped to
+ * the end of the timeline.
+ */
+uint64_t engine_seek_clamped(struct Engine *engine, uint64_t time_ms);
+
+/**
+ * Moves the playhead to the start of the timeline, e.g. for the Home key.
+ */
+void engine_seek_to_start(struct Engine *engine);
+
+/**
+ * Moves the playhead to the end of the timeline, e.g. for the End key.
+ */
+void engine_seek_to_end(struct Engine *engine);
+
+/**
+ * Pulls the playhead back to the end of the timeline if it is past it, pausing playback. Edits
+ * already do this; returns the playhead time.
+ */
+uint64_t engine_clamp_playhead(struct Engine *engine);
+
 void engine_tick(struct Engine *engine, uint64_t delta_ms);
 
+/**
+ * Snap target for a drag: the nearest clip boundary or marker within `tolerance_ms` of
+ * `time_ms`, or `time_ms` unchanged if nothing is that close.
+ */
+uint64_t engine_snap_time(const struct Engine *engine, uint64_t time_ms, uint64_t tolerance_ms);
+
+/**
+ * Jumps to the next clip boundary. Returns the new playback time in ms.
+ */
+uint64_t engine_seek_to_next_edit(struct Engine *engine);
+
+/**
+ * Jumps to the previous clip boundary. Returns the new playback time in ms.
+ */
+uint64_t engine_seek_to_prev_edit(struct Engine *engine);
+
+/**
+ * Enables looping between `start_ms` and `end_ms`. If the range is empty the whole timeline loops.
+ */
+void engine_set_loop(struct Engine *engine, bool enabled, uint64_t start_ms, uint64_t end_ms);
+
+void engine_clear_loop(struct Engine *engine);
+
+/**
+ * Sets the transport speed. Negative rates play backward; the magnitude is clamped to 0.1..=8.0
+ * and zero is ignored.
+ */
+void engine_set_playback_rate(struct Engine *engine, float rate);
+
+float engine_get_playback_rate(const struct Engine *engine);
+
+void engine_tick_frames(struct Engine *engine, uint64_t frames);
+
+/**
+ * Index of the frame under the playhead at the project frame rate.
+ */
+uint64_t engine_get_playback_frame(const struct Engine *engine);
+
+/**
+ * Sets the project frame rate to `fps / fps_den`, e.g. 30000/1001 for 29.97. Returns false if
+ * either is 0 or there's no project.
+ */
+bool engine_set_fps(struct Engine *engine, uint32_t fps, uint32_t fps_den);
+
+/**
+ * Sets the project canvas size. Returns false if either dimension is 0 or there's no project.
+ */
+bool engine_set_resolution(struct Engine *engine, uint32_t width, uint32_t height);
+
+/**
+ * Switches timecode display and parsing between drop-frame and non-drop. Drop-frame only has an
+ * effect at 29.97 and 59.94.
+ */
+void engine_set_drop_frame(struct Engine *engine, bool drop_frame);
+
+/**
+ * Sets how long newly added stills last. Existing clips keep their length. Returns false for 0
+ * or if there's no project.
+ */
+bool engine_set_default_still_duration(struct Engine *engine, uint64_t duration_ms);
+
+/**
+ * Writes the project canvas size into `width` and `height`. Returns false if there's no project.
+ */
+bool engine_get_resolution(const struct Engine *engine, uint32_t *width, uint32_t *height);
+
+double engine_get_fps(const struct Engine *engine);
+
 uint64_t engine_get_playback_time(const struct Engine *engine);
 
 bool engine_is_playing(const struct Engine *engine);
 
 struct PlaybackClipInfo *engine_get_current_playback_clip_info(const struct Engine *engine);
 
+/**
+ * Match frame: the clip and source time showing at timeline time `time_ms`, independent of the
+ * playhead. Null in a gap or past the end. Free with `free_playback_clip_info`.
+ */
+struct PlaybackClipInfo *engine_match_frame_at(const struct Engine *engine, uint64_t time_ms);
+
+/**
+ * Info for the audio clip under the playhead, for driving a separate audio player.
+ * Free with `free_playback_clip_info`.
+ */
+struct PlaybackClipInfo *engine_get_audio_clip_for_time(const struct Engine *engine);
+
+/**
+ * Aggregate numbers for the project info panel: clip count, total duration, distinct source
+ * urls and the longest and shortest clip. Gaps aren't counted. All zero without a project.
+ * The caller frees the string with `free_rust_string`.
+ */
+char *engine_get_project_stats_json(const struct Engine *engine);
+
+/**
+ * Start of every main-track clip plus the end of the last, as a JSON array of timeline ms:
+ * `[0, d0, d0 + d1, ...]`. Served from the offset cache. The caller frees the string with
+ * `free_rust_string`.
+ */
+char *engine_get_edit_points_json(const struct Engine *engine);
+
+/**
+ * Every source url used on any track or the audio lane, once each, in order of first use. Gaps
+ * aren't sources. The caller frees the string with `free_rust_string`.
+ */
+char *engine_get_distinct_sources_json(const struct Engine *engine);
+
+/**
+ * Distinct urls of the main-track clips forward playback reaches within `lookahead_ms` of the
+ * current time, starting with the one under the playhead, so Swift can preload them. Gaps and
+ * disabled clips are left out. The caller frees the string with `free_rust_string`.
+ */
+char *engine_get_upcoming_sources_json(const struct Engine *engine, uint64_t lookahead_ms);
+
+/**
+ * How often each source url is used, as a JSON object of url to clip count across every
+ * track and the audio lane. Gaps aren't counted. The caller frees the string with
+ * `free_rust_string`.
+ */
+char *engine_get_source_usage_json(const struct Engine *engine);
+
+/**
+ * Everything the transport UI needs in one consistent read: play state, time, rate, total
+ * duration and the clip under the playhead (null fields during a gap or past the end).
+ * The caller frees the string with `free_rust_string`.
+ */
+char *engine_get_playback_state_json(const struct Engine *engine);
+
 void free_playback_clip_info(struct PlaybackClipInfo *info);
 
+/**
+ * Both clips of the transition under the playhead, or null when no transition is active.
+ * Free with `free_transition_info`.
+ */
+struct TransitionInfo *engine_get_current_transition_info(const struct Engine *engine);
+
+void free_transition_info(struct TransitionInfo *info);
+
 void free_rust_string(char *ptr);
 
+/**
+ * Frees a byte buffer returned by Rust. `len` must be the length that was reported with it.
+ */
+void free_rust_bytes(uint8_t *ptr, uintptr_t len);
+
 int32_t add_one(int32_t x);
 
 int32_t multiply_by_two(int32_t x);
@@ -84,6 +900,102 @@ char *engine_get_project_as_json(struct Engine *engine);
  */
 bool engine_load_project_from_json(struct Engine *engine, const char *json_data);
 
+/**
+ * Appends the main-track clips of another project's JSON to the end of the current timeline
+ * as one edit, keeping this project's settings. The clips get fresh ids, and leave their groups
+ * behind. Gaps and clips that fail `Clip::is_valid` are left out. Returns how many were
+ * imported, or -1 if the JSON doesn't parse or there's no project.
+ */
+int64_t engine_import_project_clips(struct Engine *engine, const char *json_data);
+
+/**
+ * Serializes the current project to bincode, which is much smaller and faster than JSON for
+ * large timelines. The length is written to `out_len`; free the buffer with `free_rust_bytes`.
+ */
+uint8_t *engine_get_project_as_bincode(struct Engine *engine, uintptr_t *out_len);
+
+/**
+ * Loads a project written by `engine_get_project_as_bincode`. This resets the dirty flag.
+ * Binary projects aren't migrated, so only files from this schema version load.
+ */
+bool engine_load_project_from_bincode(struct Engine *engine, const uint8_t *data, uintptr_t len);
+
+/**
+ * Exports the main track as a CMX3600 EDL. The caller frees the string with `free_rust_string`.
+ */
+char *engine_export_edl(const struct Engine *engine);
+
+/**
+ * Exports the main track as a JSON array of clips with absolute timeline positions. This is a
+ * read-only view, not the project format. The caller frees the string with `free_rust_string`.
+ */
+char *engine_export_flattened_json(const struct Engine *engine);
+
+/**
+ * Compares two saved projects without a live engine. Returns the JSON produced by
+ * `Project::diff_json`, or null if either document can't be loaded. The caller frees the
+ * string with `free_rust_string`.
+ */
+char *project_diff_json(const char *old_json, const char *new_json);
+
+/**
+ * Exports the main track as an FCPXML document. The caller frees the string with `free_rust_string`.
+ */
+char *engine_export_fcpxml(const struct Engine *engine);
+
+/**
+ * Applies a JSON array of commands in order, e.g. `[{"RemoveClip":[0,2]},"Play"]`, and returns
+ * the resulting timeline as JSON. Returns null if the commands can't be parsed, in which case
+ * none of them are applied. The caller frees the string with `free_rust_string`.
+ */
+char *engine_apply_commands_json(struct Engine *engine, const char *json_data);
+
+/**
+ * Number of repairs made to the last loaded project. See `engine_get_last_load_warning`.
+ */
+uintptr_t engine_get_last_load_warning_count(const struct Engine *engine);
+
+/**
+ * A human-readable description of one load repair, or null for an invalid index.
+ * The caller frees the string with `free_rust_string`.
+ */
+char *engine_get_last_load_warning(const struct Engine *engine, uintptr_t idx);
+
+/**
+ * Opens a batch for programmatic edits, e.g. re-applying synced state: until
+ * `engine_end_batch`, commands change the timeline without marking the project dirty or
+ * touching its modified time. Undo, redo and autosave revisions work as usual.
+ */
+void engine_begin_batch(struct Engine *engine);
+
+/**
+ * Closes the batch opened by `engine_begin_batch`. Leaves the dirty flag as it is.
+ */
+void engine_end_batch(struct Engine *engine);
+
+/**
+ * Starts recording every handled command except ticks. Undo and redo aren't commands and
+ * aren't recorded.
+ */
+void engine_start_command_log(struct Engine *engine);
+
+/**
+ * Stops recording and discards the log.
+ */
+void engine_stop_command_log(struct Engine *engine);
+
+/**
+ * The recorded commands as a JSON array in the format `engine_apply_commands_json` takes, or
+ * null when not recording. The caller frees the string with `free_rust_string`.
+ */
+char *engine_export_command_log(const struct Engine *engine);
+
+/**
+ * Applies an exported log. For a faithful replay the engine must be in the state recording
+ * started from, e.g. a fresh project. Returns false if the log can't be parsed.
+ */
+bool engine_replay_command_log(struct Engine *engine, const char *json_data);
+
 /**
  * Sets the current file path in the engine. Swift calls this after a successful save/open.
  */
@@ -96,10 +1008,43 @@ void engine_mark_as_saved(struct Engine *engine);
 
 bool engine_new_project(struct Engine *engine, const char *name);
 
+/**
+ * Renames the current project, keeping its timeline. Returns false for an empty name or without
+ * a project.
+ */
+bool engine_rename_project(struct Engine *engine, const char *name);
+
 char *engine_get_project_name(const struct Engine *engine);
 
 char *engine_get_current_file_path(const struct Engine *engine);
 
+/**
+ * When the project was created, as an RFC 3339 string. Free with `free_rust_string`.
+ */
+char *engine_get_created_at(const struct Engine *engine);
+
+/**
+ * When the project was last edited, as an RFC 3339 string. Free with `free_rust_string`.
+ */
+char *engine_get_modified_at(const struct Engine *engine);
+
+/**
+ * Fingerprint of the timeline for sync: the same timeline always gives the same value, even
+ * after a save and reload, and any edit changes it. 0 without a project.
+ */
+uint64_t engine_get_timeline_hash(const struct Engine *engine);
+
+/**
+ * Revision of the project content, which changes with every edit. Playback doesn't count.
+ */
+uint64_t engine_get_revision(const struct Engine *engine);
+
+/**
+ * Whether anything changed since the autosave that captured `last_saved_revision`. Cheap enough
+ * to poll on a timer.
+ */
+bool engine_should_autosave(const struct Engine *engine, uint64_t last_saved_revision);
+
 bool engine_has_unsaved_changes(const struct Engine *engine);
 
 #endif  /* RUST_CORE_H */
@@ -1,505 +1,6167 @@
 use std::ffi::{CStr, CString};
-use std::os::raw::c_char;
+use std::os::raw::{c_char, c_void};
 use serde::{Serialize, Deserialize};
 
 // --------------------
 // Data model
 // --------------------
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Clip {
     pub id: String,     // unique ID
     pub url: String,    // file:// path or UUID
+    #[serde(default)]
+    pub name: String,   // display name, derived from the url when empty
     pub in_point: u64,  // ms
     pub out_point: u64, // ms
+    #[serde(default = "default_volume")]
+    pub volume: f32,    // linear gain multiplier
+    #[serde(default = "default_speed")]
+    pub speed: f32,     // playback rate, 0.5 = slow motion
+    #[serde(default)]
+    pub source_duration_ms: Option<u64>, // length of the underlying media, if probed
+    #[serde(default)]
+    pub transform: Transform,
+    #[serde(default)]
+    pub fade_in_ms: u64,  // opacity ramp from black at the start, in timeline time
+    #[serde(default)]
+    pub fade_out_ms: u64, // opacity ramp to black at the end, in timeline time
+    #[serde(default)]
+    pub poster_time_ms: u64, // source time of the thumbnail frame; Swift renders it
+    #[serde(default)]
+    pub is_offline: bool, // media file is missing, as last probed by Swift
+    #[serde(default = "default_enabled")]
+    pub enabled: bool, // disabled clips keep their time but show black
+    #[serde(default)]
+    pub label_color: u32, // packed RGBA tag for organizing; 0 = none, display only
+    #[serde(default)]
+    pub group_id: Option<String>, // clips sharing a group move and remove together
+    #[serde(default)]
+    pub freeze: bool, // hold a single frame for the whole clip
+    #[serde(default)]
+    pub freeze_frame_ms: Option<u64>, // source time held while frozen; None = the in point
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Default)]
-pub struct Timeline {
-    pub clips: Vec<Clip>, // magnetic ordering
+/// Placement of a clip on the canvas. Stored for the compositor; the engine doesn't apply it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Transform {
+    pub x: f32,            // offset in pixels from the canvas center
+    pub y: f32,
+    pub scale: f32,        // 1.0 = native size, must be > 0
+    pub rotation_deg: f32, // clockwise
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct Project {
-    pub name: String,
-    pub timeline: Timeline,
-    pub created_at: String,
-    pub modified_at: String,
+impl Default for Transform {
+    fn default() -> Self {
+        Self { x: 0.0, y: 0.0, scale: 1.0, rotation_deg: 0.0 }
+    }
 }
 
-impl Project {
-    pub fn new(name: String) -> Self {
-        let now = chrono::Utc::now().to_rfc3339();
-        Self {
-            name,
-            timeline: Timeline::default(),
-            created_at: now.clone(),
-            modified_at: now,
-        }
+impl Transform {
+    pub fn is_valid(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite() && self.rotation_deg.is_finite()
+            && self.scale.is_finite() && self.scale > 0.0
     }
+}
 
-    pub fn update_modified_time(&mut self) {
-        self.modified_at = chrono::Utc::now().to_rfc3339();
+/// Last path component of a url, e.g. "beach.mov" for "file:///Users/me/beach.mov".
+fn name_from_url(url: &str) -> String {
+    match url.trim_end_matches('/').rsplit('/').next() {
+        Some(last) if !last.is_empty() => last.to_string(),
+        _ => url.to_string(),
     }
 }
 
-// --------------------
-// Commands (from Swift)
-// --------------------
-pub enum Command {
-    AddClip(Clip, usize),   // insert at index
-    RemoveClip(usize),      // remove by index
-    CutClip(usize, u64),    // cut clip at index at specified position (ms)
-    UpdateClipRange(usize, u64, u64), // update in/out points of a clip
-    Play,
-    Pause,
-    Seek(u64),
-    Tick(u64), // delta_ms
+fn default_volume() -> f32 {
+    1.0
 }
 
-// --------------------
-// Playback
-// --------------------
-#[derive(Clone, Debug, Default)]
-pub struct PlaybackState {
-    pub is_playing: bool,
-    pub time_ms: u64, // Global timeline time
+fn default_speed() -> f32 {
+    1.0
 }
 
-// Struct to pass playback info over FFI
-#[repr(C)]
-pub struct PlaybackClipInfo {
-    pub id: *mut c_char,
-    pub url: *mut c_char,
-    pub time_in_clip_ms: u64,
+fn default_enabled() -> bool {
+    true
 }
 
-// --------------------
-// Engine (timeline only)
-// --------------------
-pub struct Engine {
-    pub project: Option<Project>,
-    pub current_file_path: Option<String>,
-    pub is_dirty: bool,
-    pub playback_state: PlaybackState,
-}
+impl Clip {
+    pub fn new(id: String, url: String, in_point: u64, out_point: u64) -> Self {
+        let name = name_from_url(&url);
+        Self { id, url, name, in_point, out_point, volume: default_volume(), speed: default_speed(), source_duration_ms: None, transform: Transform::default(), fade_in_ms: 0, fade_out_ms: 0, poster_time_ms: in_point, is_offline: false, enabled: default_enabled(), label_color: 0, group_id: None, freeze: false, freeze_frame_ms: None }
+    }
 
-pub enum EngineEvent {
-    TimelineChanged(Timeline),
-}
+    /// Creates an empty placeholder that occupies `duration` ms of the timeline.
+    pub fn gap(id: String, duration: u64) -> Self {
+        Self::new(id, String::new(), 0, duration)
+    }
 
-impl Engine {
-    pub fn new() -> Self {
-        Self { 
-            project: Some(Project::new("Untitled Project".to_string())),
-            current_file_path: None,
-            is_dirty: true, // A new project is unsaved.
-            playback_state: PlaybackState::default(),
-        }
+    /// Length of the clip in ms. Saturates so a malformed clip can't underflow.
+    pub fn duration(&self) -> u64 {
+        self.out_point.saturating_sub(self.in_point)
     }
 
-    pub fn get_timeline(&self) -> Timeline {
-        self.project.as_ref().map(|p| p.timeline.clone()).unwrap_or_default()
+    /// Limits a point to the end of the source media when its length is known.
+    pub fn clamp_to_source(&self, point: u64) -> u64 {
+        self.source_duration_ms.map_or(point, |d| point.min(d))
     }
 
-    pub fn handle(&mut self, cmd: Command) -> EngineEvent {
-        if let Some(ref mut project) = self.project {
-            match &cmd {
-                Command::AddClip(clip, idx) => {
-                    if *idx <= project.timeline.clips.len() {
-                        project.timeline.clips.insert(*idx, clip.clone());
-                    } else {
-                        project.timeline.clips.push(clip.clone());
-                    }
-                }
-                Command::RemoveClip(idx) => {
-                    if *idx < project.timeline.clips.len() {
-                        project.timeline.clips.remove(*idx);
-                    }
-                }
-                Command::CutClip(idx, position) => {
-                    if *idx < project.timeline.clips.len() {
-                        let clip = &project.timeline.clips[*idx];
-                        
-                        // Only cut if position is within the clip's range
-                        if *position > clip.in_point && *position < clip.out_point {
-                            // Create two new fully independent clips from the original
-                            let timestamp = std::time::SystemTime::now()
-                                .duration_since(std::time::UNIX_EPOCH)
-                                .unwrap_or_default()
-                                .as_millis();
-                            
-                            // Use unique identifiers for the new clips
-                            let first_clip = Clip {
-                                id: format!("{}-{}-A", clip.id, timestamp),
-                                url: clip.url.clone(),
-                                in_point: clip.in_point,
-                                out_point: *position,
-                            };
-                            
-                            let second_clip = Clip {
-                                id: format!("{}-{}-B", clip.id, timestamp),
-                                url: clip.url.clone(),
-                                in_point: *position,
-                                out_point: clip.out_point,
-                            };
-                            
-                            // Remove the original and insert the two new clips
-                            project.timeline.clips.remove(*idx);
-                            project.timeline.clips.insert(*idx, second_clip);
-                            project.timeline.clips.insert(*idx, first_clip);
-                        }
-                    }
-                }
-                Command::UpdateClipRange(idx, in_point, out_point) => {
-                    if *idx < project.timeline.clips.len() {
-                        let clip = &mut project.timeline.clips[*idx];
-                        
-                        // Only update if the new range is valid
-                        if *in_point < *out_point {
-                            clip.in_point = *in_point;
-                            clip.out_point = *out_point;
-                        }
-                    }
-                }
-                Command::Play => self.playback_state.is_playing = true,
-                Command::Pause => self.playback_state.is_playing = false,
-                Command::Seek(time) => {
-                    let total_duration = project.timeline.clips.iter().map(|c| c.out_point - c.in_point).sum();
-                    self.playback_state.time_ms = (*time).min(total_duration);
-                },
-                Command::Tick(delta_ms) => {
-                    if self.playback_state.is_playing {
-                        let total_duration: u64 = project.timeline.clips.iter().map(|c| c.out_point - c.in_point).sum();
-                        let new_time = self.playback_state.time_ms + *delta_ms;
-                        if new_time >= total_duration {
-                            self.playback_state.time_ms = total_duration;
-                            self.playback_state.is_playing = false;
-                        } else {
-                            self.playback_state.time_ms = new_time;
-                        }
-                    }
-                }
-            }
-            if !matches!(cmd, Command::Tick(_)) {
-                project.update_modified_time();
-                self.is_dirty = true; // Any command makes the project dirty.
-            }
-            EngineEvent::TimelineChanged(project.timeline.clone())
-        } else {
-            EngineEvent::TimelineChanged(Timeline::default())
-        }
+    // A malformed speed from untrusted JSON plays at normal rate rather than dividing by zero.
+    fn effective_speed(&self) -> f64 {
+        if self.speed.is_finite() && self.speed > 0.0 { self.speed as f64 } else { 1.0 }
     }
 
-    pub fn get_clip_for_time(&self) -> Option<(Clip, u64)> { // (Clip, time_within_clip)
-        if let Some(ref project) = self.project {
-            let mut current_time: u64 = 0;
-            for clip in &project.timeline.clips {
-                let clip_duration = clip.out_point - clip.in_point;
-                if self.playback_state.time_ms >= current_time && self.playback_state.time_ms < current_time + clip_duration {
-                    let time_within_clip = clip.in_point + (self.playback_state.time_ms - current_time);
-                    return Some((clip.clone(), time_within_clip));
-                }
-                current_time += clip_duration;
-            }
+    /// Time the clip occupies on the timeline once its speed is applied.
+    pub fn timeline_duration(&self) -> u64 {
+        (self.duration() as f64 / self.effective_speed()).round() as u64
+    }
+
+    /// Maps an offset into the clip's timeline span back to a source time. A freeze frame
+    /// always shows its held frame.
+    pub fn source_time_at(&self, offset_ms: u64) -> u64 {
+        if self.freeze {
+            return self.freeze_frame_ms.unwrap_or(self.in_point);
         }
-        None
+        let source_offset = (offset_ms as f64 * self.effective_speed()).floor() as u64;
+        self.in_point + source_offset.min(self.duration().saturating_sub(1))
     }
-}
 
-// --------------------
-// FFI Boundary
-// --------------------
+    /// Offset into the clip's timeline span that shows a source time; the inverse of
+    /// `source_time_at`. Times outside the clip's range land on its first or last frame.
+    pub fn offset_of_source_time(&self, source_ms: u64) -> u64 {
+        let offset = (source_ms.saturating_sub(self.in_point) as f64 / self.effective_speed()).floor() as u64;
+        offset.min(self.timeline_duration().saturating_sub(1))
+    }
 
-#[no_mangle]
-pub extern "C" fn engine_new() -> *mut Engine {
-    Box::into_raw(Box::new(Engine::new()))
-}
+    /// Source media this clip needs before its in point to play `duration_ms` of lead-in, as
+    /// the incoming side of a transition. A freeze frame needs none.
+    pub fn lead_in_source_ms(&self, duration_ms: u64) -> u64 {
+        if self.freeze { 0 } else { (duration_ms as f64 * self.effective_speed()).round() as u64 }
+    }
 
-#[no_mangle]
-pub extern "C" fn engine_free(engine: *mut Engine) {
-    if !engine.is_null() {
-        unsafe { let _ = Box::from_raw(engine); }
+    /// The poster time, kept inside the clip's current range even after trims.
+    pub fn poster_time(&self) -> u64 {
+        self.poster_time_ms.clamp(self.in_point, self.out_point.max(self.in_point))
     }
-}
 
-#[no_mangle]
-pub extern "C" fn engine_add_clip(engine: *mut Engine, id: *const c_char, url: *const c_char, in_ms: u64, out_ms: u64, idx: usize) {
-    if engine.is_null() { return; }
-    let eng = unsafe { &mut *engine };
-    let id = unsafe { CStr::from_ptr(id).to_string_lossy().into_owned() };
-    let url = unsafe { CStr::from_ptr(url).to_string_lossy().into_owned() };
-    let clip = Clip { id, url, in_point: in_ms, out_point: out_ms };
-    eng.handle(Command::AddClip(clip, idx));
-}
+    /// Opacity from the fade ramps at an offset into the clip's timeline span, 0.0 to 1.0.
+    pub fn opacity_at(&self, offset_ms: u64) -> f32 {
+        let duration = self.timeline_duration();
+        let offset = offset_ms.min(duration);
+        let fade_in = if offset < self.fade_in_ms { offset as f32 / self.fade_in_ms as f32 } else { 1.0 };
+        let remaining = duration - offset;
+        let fade_out = if remaining < self.fade_out_ms { remaining as f32 / self.fade_out_ms as f32 } else { 1.0 };
+        fade_in.min(fade_out)
+    }
 
-#[no_mangle]
-pub extern "C" fn engine_remove_clip(engine: *mut Engine, idx: usize) {
-    if engine.is_null() { return; }
-    let eng = unsafe { &mut *engine };
-    eng.handle(Command::RemoveClip(idx));
-}
+    /// Whether callers may add this clip: it needs a url and a non-empty range. Gaps fail this;
+    /// only the engine creates them.
+    pub fn is_valid(&self) -> bool {
+        !self.url.is_empty() && self.out_point > self.in_point
+    }
 
-#[no_mangle]
-pub extern "C" fn engine_cut_clip(engine: *mut Engine, idx: usize, position: u64) {
-    if engine.is_null() { return; }
-    let eng = unsafe { &mut *engine };
-    eng.handle(Command::CutClip(idx, position));
+    /// Whether `next` carries on exactly where this clip stops in the same media, so the two can
+    /// be joined into one.
+    pub fn joins_with(&self, next: &Clip) -> bool {
+        self.url == next.url && self.out_point == next.in_point
+    }
+
+    /// Gaps have no media behind them; playback shows black.
+    pub fn is_gap(&self) -> bool {
+        self.url.is_empty()
+    }
 }
 
-#[no_mangle]
-pub extern "C" fn engine_update_clip_range(engine: *mut Engine, idx: usize, in_point: u64, out_point: u64) {
-    if engine.is_null() { return; }
-    let eng = unsafe { &mut *engine };
-    eng.handle(Command::UpdateClipRange(idx, in_point, out_point));
+/// Index of the primary magnetic video track. Every timeline has one.
+pub const MAIN_TRACK: usize = 0;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TrackKind {
+    Video,
+    Audio,
+    Overlay,
 }
 
-#[no_mangle]
-pub extern "C" fn engine_get_clip_count(engine: *const Engine) -> usize {
-    if engine.is_null() { return 0; }
-    let eng = unsafe { &*engine };
-    eng.project.as_ref().map_or(0, |p| p.timeline.clips.len())
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Track {
+    pub id: String,
+    pub kind: TrackKind,
+    pub clips: Vec<Clip>, // magnetic ordering
 }
 
-#[no_mangle]
-pub extern "C" fn engine_get_clip_id(engine: *const Engine, idx: usize) -> *mut c_char {
-    if engine.is_null() { return std::ptr::null_mut(); }
-    let eng = unsafe { &*engine };
-    if let Some(clip) = eng.project.as_ref().and_then(|p| p.timeline.clips.get(idx)) {
-        CString::new(clip.id.clone()).unwrap().into_raw()
-    } else {
-        std::ptr::null_mut()
+impl Track {
+    pub fn new(id: String, kind: TrackKind) -> Self {
+        Self { id, kind, clips: Vec::new() }
     }
-}
 
-#[no_mangle]
-pub extern "C" fn engine_get_clip_url(engine: *const Engine, idx: usize) -> *mut c_char {
-    if engine.is_null() { return std::ptr::null_mut(); }
-    let eng = unsafe { &*engine };
-    if let Some(clip) = eng.project.as_ref().and_then(|p| p.timeline.clips.get(idx)) {
-        CString::new(clip.url.clone()).unwrap().into_raw()
-    } else {
-        std::ptr::null_mut()
+    pub fn duration(&self) -> u64 {
+        self.clips.iter().map(|c| c.timeline_duration()).sum()
     }
-}
 
-#[no_mangle]
-pub extern "C" fn engine_get_clip_in_point(engine: *const Engine, idx: usize) -> u64 {
-    if engine.is_null() { return 0; }
-    let eng = unsafe { &*engine };
-    eng.project.as_ref().and_then(|p| p.timeline.clips.get(idx)).map_or(0, |c| c.in_point)
-}
+    /// Timeline start of every clip, followed by the end of the track.
+    pub fn boundaries(&self) -> Vec<u64> {
+        let mut boundaries = Vec::with_capacity(self.clips.len() + 1);
+        let mut current_time: u64 = 0;
+        boundaries.push(current_time);
+        for clip in &self.clips {
+            current_time += clip.timeline_duration();
+            boundaries.push(current_time);
+        }
+        boundaries
+    }
 
-#[no_mangle]
-pub extern "C" fn engine_get_clip_out_point(engine: *const Engine, idx: usize) -> u64 {
-    if engine.is_null() { return 0; }
-    let eng = unsafe { &*engine };
-    eng.project.as_ref().and_then(|p| p.timeline.clips.get(idx)).map_or(0, |c| c.out_point)
-}
+    /// Makes sure a clip boundary falls at `time_ms`, cutting the clip under it in two if needed.
+    /// Returns the index of the clip that now starts there (the clip count past the end).
+    pub fn split_at(&mut self, time_ms: u64, next_id: &mut u64) -> usize {
+        let boundaries = self.boundaries();
+        let idx = boundaries.partition_point(|&b| b <= time_ms).saturating_sub(1);
+        if idx >= self.clips.len() {
+            return self.clips.len();
+        }
+        let position = self.cut_position(idx, time_ms - boundaries[idx]);
+        let clip = &self.clips[idx];
+        if position <= clip.in_point {
+            return idx;
+        }
+        if position >= clip.out_point {
+            return idx + 1;
+        }
+        self.cut_clip(idx, position, 1, next_id);
+        idx + 1
+    }
 
-// Playback FFI functions
-#[no_mangle]
-pub extern "C" fn engine_play(engine: *mut Engine) {
-    if engine.is_null() { return; }
-    let eng = unsafe { &mut *engine };
-    eng.handle(Command::Play);
+    // Source time at an offset into the clip's timeline span, unclamped so it can land on the out point.
+    fn cut_position(&self, idx: usize, offset_ms: u64) -> u64 {
+        let clip = &self.clips[idx];
+        clip.in_point + (offset_ms as f64 * clip.effective_speed()).round() as u64
+    }
+
+    /// Cuts the clip at `idx` at source time `position` into two independent clips with fresh ids.
+    /// Returns false, leaving the track alone, if either half would be shorter than `min_duration`.
+    pub fn cut_clip(&mut self, idx: usize, position: u64, min_duration: u64, next_id: &mut u64) -> bool {
+        let Some(clip) = self.clips.get(idx) else { return false };
+        if position < clip.in_point + min_duration || position + min_duration > clip.out_point {
+            return false;
+        }
+
+        // Use unique identifiers for the new clips
+        let first_clip = Clip { id: gen_id(next_id, &clip.id), out_point: position, ..clip.clone() };
+        let second_clip = Clip { id: gen_id(next_id, &clip.id), in_point: position, ..clip.clone() };
+        self.clips.splice(idx..=idx, [first_clip, second_clip]);
+        true
+    }
+
+    /// Like `clip_at`, but binary-searches precomputed `boundaries` instead of walking the clips.
+    pub fn clip_at_with(&self, boundaries: &[u64], time_ms: u64) -> Option<(&Clip, u64)> {
+        // Last clip starting at or before the time; zero-length clips are skipped naturally
+        let idx = boundaries.partition_point(|&b| b <= time_ms).checked_sub(1)?;
+        self.clips.get(idx).map(|clip| (clip, time_ms - boundaries[idx]))
+    }
+
+    /// Finds the clip under `time_ms`, returning it with the offset into the clip.
+    pub fn clip_at(&self, time_ms: u64) -> Option<(&Clip, u64)> {
+        let mut current_time: u64 = 0;
+        for clip in &self.clips {
+            let clip_duration = clip.timeline_duration();
+            if time_ms >= current_time && time_ms < current_time + clip_duration {
+                return Some((clip, time_ms - current_time));
+            }
+            current_time += clip_duration;
+        }
+        None
+    }
 }
 
-#[no_mangle]
-pub extern "C" fn engine_pause(engine: *mut Engine) {
-    if engine.is_null() { return; }
-    let eng = unsafe { &mut *engine };
-    eng.handle(Command::Pause);
+/// An audio clip placed at an absolute timeline position, independent of the magnetic tracks.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AudioClip {
+    pub start_ms: u64, // timeline position
+    pub clip: Clip,
 }
 
-#[no_mangle]
-pub extern "C" fn engine_seek(engine: *mut Engine, time_ms: u64) {
-    if engine.is_null() { return; }
-    let eng = unsafe { &mut *engine };
-    eng.handle(Command::Seek(time_ms));
+impl AudioClip {
+    pub fn end_ms(&self) -> u64 {
+        self.start_ms + self.clip.timeline_duration()
+    }
 }
 
-#[no_mangle]
-pub extern "C" fn engine_tick(engine: *mut Engine, delta_ms: u64) {
-    if engine.is_null() { return; }
-    let eng = unsafe { &mut *engine };
-    eng.handle(Command::Tick(delta_ms));
+/// A named point on the timeline for navigation. Not part of any track.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Marker {
+    pub id: String,
+    pub time_ms: u64, // timeline position
+    pub label: String,
+    pub color: u32,   // packed RGBA
 }
 
-#[no_mangle]
-pub extern "C" fn engine_get_playback_time(engine: *const Engine) -> u64 {
-    if engine.is_null() { return 0; }
-    let eng = unsafe { &*engine };
-    eng.playback_state.time_ms
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TransitionKind {
+    CrossDissolve,
 }
 
-#[no_mangle]
-pub extern "C" fn engine_is_playing(engine: *const Engine) -> bool {
-    if engine.is_null() { return false; }
-    let eng = unsafe { &*engine };
-    eng.playback_state.is_playing
+/// A transition between two neighbouring clips on a track. It ends at the cut: the last
+/// `duration_ms` of the outgoing clip play over the incoming clip's lead-in, taken from the
+/// source media just before its in point, so the incoming clip needs that much handle.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Transition {
+    pub from_clip_id: String,
+    pub to_clip_id: String,
+    pub duration_ms: u64,
+    pub kind: TransitionKind,
 }
 
-#[no_mangle]
-pub extern "C" fn engine_get_current_playback_clip_info(engine: *const Engine) -> *mut PlaybackClipInfo {
-    if engine.is_null() { return std::ptr::null_mut(); }
-    let eng = unsafe { &*engine };
+/// Both sides of a transition that is in progress at some timeline time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActiveTransition {
+    pub from: (Clip, u64), // (outgoing clip, source time)
+    pub to: (Clip, u64),   // (incoming clip, source time)
+    pub progress: f32,     // 0.0 at the start of the transition, approaching 1.0 at the cut
+    pub kind: TransitionKind,
+}
 
-    if let Some((clip, time_in_clip_ms)) = eng.get_clip_for_time() {
-        let info = Box::new(PlaybackClipInfo {
-            id: CString::new(clip.id).unwrap().into_raw(),
-            url: CString::new(clip.url).unwrap().into_raw(),
-            time_in_clip_ms,
-        });
-        Box::into_raw(info)
-    } else {
-        std::ptr::null_mut()
-    }
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Timeline {
+    pub tracks: Vec<Track>, // stacking order: later tracks draw on top
+    #[serde(default)]
+    pub audio_clips: Vec<AudioClip>, // free-floating, may overlap video and each other
+    #[serde(default)]
+    pub markers: Vec<Marker>, // sorted by time
+    #[serde(default)]
+    pub transitions: Vec<Transition>,
 }
 
-#[no_mangle]
-pub extern "C" fn free_playback_clip_info(info: *mut PlaybackClipInfo) {
-    if !info.is_null() {
-        unsafe {
-            let a = Box::from_raw(info);
-            // The strings inside were created with CString::into_raw, so we must free them.
-            let _ = CString::from_raw(a.id);
-            let _ = CString::from_raw(a.url);
+impl Default for Timeline {
+    fn default() -> Self {
+        Self {
+            tracks: vec![Track::new("main".to_string(), TrackKind::Video)],
+            audio_clips: Vec::new(),
+            markers: Vec::new(),
+            transitions: Vec::new(),
         }
     }
 }
 
-// Free string resources allocated by Rust
-#[no_mangle]
-pub extern "C" fn free_rust_string(ptr: *mut c_char) {
-    if !ptr.is_null() {
-        unsafe { let _ = CString::from_raw(ptr); }
+impl Timeline {
+    /// Clips on `track`, or an empty slice if the track doesn't exist.
+    pub fn clips(&self, track: usize) -> &[Clip] {
+        self.tracks.get(track).map_or(&[], |t| &t.clips)
     }
-}
 
-// Test functions for reference (defined in the header file already)
-#[no_mangle]
-pub extern "C" fn add_one(x: i32) -> i32 {
-    x + 1
+    pub fn clips_mut(&mut self, track: usize) -> Option<&mut Vec<Clip>> {
+        self.tracks.get_mut(track).map(|t| &mut t.clips)
+    }
+
+    /// Inserts a marker keeping the list sorted by time.
+    pub fn insert_marker(&mut self, marker: Marker) {
+        let idx = self.markers.partition_point(|m| m.time_ms <= marker.time_ms);
+        self.markers.insert(idx, marker);
+    }
+
+    /// The first marker strictly after `time_ms`.
+    pub fn next_marker_after(&self, time_ms: u64) -> Option<&Marker> {
+        self.markers.iter().find(|m| m.time_ms > time_ms)
+    }
+
+    /// The transition in progress at `time_ms` on `track`, if any. Transitions whose clips are no
+    /// longer neighbours, or whose incoming clip was trimmed back past its handle, are ignored.
+    pub fn transition_at(&self, track: usize, time_ms: u64) -> Option<ActiveTransition> {
+        let track = self.tracks.get(track)?;
+        let boundaries = track.boundaries();
+        for transition in &self.transitions {
+            let Some(idx) = track.clips.iter().position(|c| c.id == transition.from_clip_id) else { continue };
+            let Some(to) = track.clips.get(idx + 1).filter(|c| c.id == transition.to_clip_id) else { continue };
+            if to.lead_in_source_ms(transition.duration_ms) > to.in_point {
+                continue;
+            }
+            let from = &track.clips[idx];
+            let cut = boundaries[idx + 1];
+            let start = cut.saturating_sub(transition.duration_ms);
+            if time_ms >= start && time_ms < cut && transition.duration_ms > 0 {
+                let from_time = from.source_time_at(time_ms - boundaries[idx]);
+                // The incoming clip runs up to its in point, hitting it exactly at the cut
+                let to_time = to.in_point - to.lead_in_source_ms(cut - time_ms);
+                return Some(ActiveTransition {
+                    from: (from.clone(), from_time),
+                    to: (to.clone(), to_time),
+                    progress: (time_ms - start) as f32 / transition.duration_ms as f32,
+                    kind: transition.kind,
+                });
+            }
+        }
+        None
+    }
+
+    /// Every clip on every track, then the audio lane.
+    pub fn all_clips(&self) -> impl Iterator<Item = &Clip> {
+        self.tracks.iter().flat_map(|t| t.clips.iter()).chain(self.audio_clips.iter().map(|a| &a.clip))
+    }
+
+    pub fn all_clips_mut(&mut self) -> impl Iterator<Item = &mut Clip> {
+        self.tracks.iter_mut().flat_map(|t| t.clips.iter_mut()).chain(self.audio_clips.iter_mut().map(|a| &mut a.clip))
+    }
+
+    /// Finds a clip on any track or in the audio lane by id.
+    pub fn clip_by_id(&self, id: &str) -> Option<&Clip> {
+        self.all_clips().find(|c| c.id == id)
+    }
+
+    /// Every clip boundary across all tracks, sorted and deduplicated.
+    pub fn edit_points(&self) -> Vec<u64> {
+        let mut points: Vec<u64> = self.tracks.iter().flat_map(|t| t.boundaries()).collect();
+        points.sort_unstable();
+        points.dedup();
+        points
+    }
+
+    /// The timeline ends where its longest track ends.
+    pub fn total_duration(&self) -> u64 {
+        self.tracks.iter().map(|t| t.duration()).max().unwrap_or(0)
+    }
+
+    /// Fingerprint of everything on the timeline: clip order, ids, urls, ranges and every other
+    /// field. Equal timelines always hash the same, across runs and reloads.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = Fnv1a::default();
+        serde_json::to_writer(&mut hasher, self).expect("timelines always serialize");
+        hasher.0
+    }
+
+    /// Drops clips whose range is empty or inverted. Returns the number removed.
+    pub fn remove_invalid_clips(&mut self) -> usize {
+        let mut removed = 0;
+        for track in &mut self.tracks {
+            let count_before = track.clips.len();
+            track.clips.retain(|c| c.out_point > c.in_point);
+            removed += count_before - track.clips.len();
+        }
+        let audio_count_before = self.audio_clips.len();
+        self.audio_clips.retain(|a| a.clip.out_point > a.clip.in_point);
+        removed += audio_count_before - self.audio_clips.len();
+        removed
+    }
+
+    /// Fixes what it can in a timeline from an untrusted file: swaps inverted ranges, drops empty
+    /// clips and renames duplicate ids. Returns a warning for each repair made.
+    pub fn validate_and_repair(&mut self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.tracks.is_empty() {
+            self.tracks = Timeline::default().tracks;
+            warnings.push("Timeline had no tracks; added an empty main track".to_string());
+        }
+        self.markers.sort_by_key(|m| m.time_ms);
+
+        // Clips saved before they had names get them from their urls
+        for clip in self.all_clips_mut() {
+            if clip.name.is_empty() {
+                clip.name = name_from_url(&clip.url);
+            }
+        }
+
+        for clip in self.all_clips_mut() {
+            if clip.out_point < clip.in_point {
+                std::mem::swap(&mut clip.in_point, &mut clip.out_point);
+                warnings.push(format!("Clip '{}' had an inverted range; swapped its in and out points", clip.id));
+            }
+        }
+
+        for track in &mut self.tracks {
+            track.clips.retain(|c| {
+                let keep = c.out_point > c.in_point;
+                if !keep {
+                    warnings.push(format!("Clip '{}' had an empty range and was removed", c.id));
+                }
+                keep
+            });
+        }
+        self.audio_clips.retain(|a| {
+            let keep = a.clip.out_point > a.clip.in_point;
+            if !keep {
+                warnings.push(format!("Audio clip '{}' had an empty range and was removed", a.clip.id));
+            }
+            keep
+        });
+
+        // Ids must be unique across every track and the audio lane
+        let mut seen = std::collections::HashSet::new();
+        for clip in self.all_clips_mut() {
+            if !seen.insert(clip.id.clone()) {
+                let mut n = 2;
+                while seen.contains(&format!("{}-{}", clip.id, n)) {
+                    n += 1;
+                }
+                let new_id = format!("{}-{}", clip.id, n);
+                warnings.push(format!("Duplicate clip id '{}' renamed to '{}'", clip.id, new_id));
+                seen.insert(new_id.clone());
+                clip.id = new_id;
+            }
+        }
+        warnings
+    }
 }
 
-#[no_mangle]
-pub extern "C" fn multiply_by_two(x: i32) -> i32 {
-    x * 2
+// 64-bit FNV-1a. Unlike `DefaultHasher` it has no random seed, so hashes are stable.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(0xcbf29ce484222325)
+    }
 }
 
-#[no_mangle]
-pub extern "C" fn divide_by_two(x: i32) -> i32 {
-    x / 2
+impl std::io::Write for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x100000001b3);
+        }
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
 }
 
-// Project management FFI functions are now data-oriented, not file-oriented.
+/// Version of the saved project format written by this build. Bump it whenever the format
+/// changes and add the upgrade step to `migrate_project_json`.
+pub const PROJECT_SCHEMA_VERSION: u32 = 2;
 
-/// Serializes the current project to a JSON string.
-/// The caller is responsible for freeing the returned string with `free_rust_string`.
-#[no_mangle]
-pub extern "C" fn engine_get_project_as_json(engine: *mut Engine) -> *mut c_char {
-    if engine.is_null() { return std::ptr::null_mut(); }
-    let eng = unsafe { &mut *engine };
+/// Upgrades a project document from any older schema to `PROJECT_SCHEMA_VERSION`, one version
+/// at a time. Fails if the document comes from a newer build.
+pub fn migrate_project_json(doc: &mut serde_json::Value) -> Result<(), String> {
+    let Some(project) = doc.as_object_mut() else { return Err("project is not a JSON object".to_string()) };
+    let mut version = project.get("schema_version").and_then(|v| v.as_u64()).unwrap_or(1);
+    if version > PROJECT_SCHEMA_VERSION as u64 {
+        return Err(format!("project uses schema version {}, but this build only supports up to {}", version, PROJECT_SCHEMA_VERSION));
+    }
 
-    if let Some(ref project) = eng.project {
-        match serde_json::to_string_pretty(project) {
-            Ok(json_string) => CString::new(json_string).unwrap().into_raw(),
-            Err(_) => std::ptr::null_mut(),
+    if version == 1 {
+        // v1 timelines held a single `clips` list; it becomes the main video track
+        if let Some(timeline) = project.get_mut("timeline").and_then(|t| t.as_object_mut()) {
+            let clips = timeline.remove("clips");
+            if !timeline.contains_key("tracks") {
+                let main = serde_json::json!({ "id": "main", "kind": "Video", "clips": clips.unwrap_or_else(|| serde_json::json!([])) });
+                timeline.insert("tracks".to_string(), serde_json::json!([main]));
+            }
         }
-    } else {
-        std::ptr::null_mut()
+        version = 2;
     }
+
+    project.insert("schema_version".to_string(), serde_json::json!(version));
+    Ok(())
 }
 
-/// Loads a project from a JSON string. This resets the dirty flag.
-#[no_mangle]
-pub extern "C" fn engine_load_project_from_json(engine: *mut Engine, json_data: *const c_char) -> bool {
-    if engine.is_null() || json_data.is_null() { return false; }
-    let eng = unsafe { &mut *engine };
-    let json = unsafe { CStr::from_ptr(json_data).to_string_lossy() };
+fn default_schema_version() -> u32 {
+    1
+}
 
-    match serde_json::from_str(&json) {
-        Ok(project) => {
-            eng.project = Some(project);
-            eng.current_file_path = None; // Path is unknown until Swift sets it.
-            eng.is_dirty = false; // A freshly loaded project is not dirty.
-            true
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Project {
+    #[serde(default = "default_schema_version")]
+    pub schema_version: u32, // format version the project was written with; see PROJECT_SCHEMA_VERSION
+    pub name: String,
+    pub timeline: Timeline,
+    pub created_at: String,
+    pub modified_at: String,
+    // Frame rate as the ratio fps / fps_den, e.g. 30000/1001 for 29.97.
+    #[serde(default = "default_fps")]
+    pub fps: u32,
+    #[serde(default = "default_fps_den")]
+    pub fps_den: u32,
+    #[serde(default = "default_width")]
+    pub width: u32,  // canvas size in pixels
+    #[serde(default = "default_height")]
+    pub height: u32,
+    #[serde(default)]
+    pub drop_frame: bool, // drop-frame timecode; only affects 29.97 and 59.94
+    #[serde(default = "default_still_duration_ms")]
+    pub default_still_duration_ms: u64, // length given to newly added still images
+}
+
+/// The project-level settings that live outside the timeline, as reported by `ProjectChanged`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ProjectSettings {
+    pub name: String,
+    pub fps: u32,
+    pub fps_den: u32,
+    pub width: u32,
+    pub height: u32,
+    pub drop_frame: bool,
+    pub default_still_duration_ms: u64,
+}
+
+fn default_fps() -> u32 {
+    30
+}
+
+fn default_fps_den() -> u32 {
+    1
+}
+
+fn default_width() -> u32 {
+    1920
+}
+
+fn default_height() -> u32 {
+    1080
+}
+
+fn default_still_duration_ms() -> u64 {
+    5000
+}
+
+impl Project {
+    /// Parses a saved project, upgrading it from an older schema first. Doesn't validate or
+    /// repair the timeline; `Engine::load_project` does that.
+    pub fn from_json(json: &str) -> Result<Project, String> {
+        let mut doc = serde_json::from_str::<serde_json::Value>(json).map_err(|e| e.to_string())?;
+        migrate_project_json(&mut doc)?;
+        serde_json::from_value::<Project>(doc).map_err(|e| e.to_string())
+    }
+
+    pub fn new(name: String) -> Self {
+        let now = chrono::Utc::now().to_rfc3339();
+        Self {
+            schema_version: PROJECT_SCHEMA_VERSION,
+            name,
+            timeline: Timeline::default(),
+            created_at: now.clone(),
+            modified_at: now,
+            fps: default_fps(),
+            fps_den: default_fps_den(),
+            width: default_width(),
+            height: default_height(),
+            drop_frame: false,
+            default_still_duration_ms: default_still_duration_ms(),
+        }
+    }
+
+    /// Start time of `frame` in ms, rounded up so that `ms_to_frame` maps it back to the same frame.
+    /// Computed from the frame index each time, so stepping frame by frame never accumulates drift.
+    /// Saturates for frames beyond the range of a `u64` of ms.
+    pub fn frame_to_ms(&self, frame: u64) -> u64 {
+        let num = frame as u128 * 1000 * self.fps_den as u128;
+        u64::try_from(num.div_ceil(self.fps.max(1) as u128)).unwrap_or(u64::MAX)
+    }
+
+    /// Rounds `time_ms` to the nearest frame boundary.
+    pub fn snap_to_frame(&self, time_ms: u64) -> u64 {
+        let frame = self.ms_to_frame(time_ms);
+        let (before, after) = (self.frame_to_ms(frame), self.frame_to_ms(frame + 1));
+        if after.abs_diff(time_ms) < time_ms.abs_diff(before) { after } else { before }
+    }
+
+    /// Index of the frame on screen at `time_ms`.
+    pub fn ms_to_frame(&self, time_ms: u64) -> u64 {
+        let num = time_ms as u128 * self.fps as u128;
+        (num / (1000 * self.fps_den.max(1) as u128)) as u64
+    }
+
+    /// Whole frames per second used for timecode, e.g. 30 for 29.97.
+    pub fn timecode_fps(&self) -> u64 {
+        let den = self.fps_den.max(1) as u64;
+        ((self.fps as u64 + den / 2) / den).max(1)
+    }
+
+    /// Frame numbers skipped at the start of each minute in drop-frame timecode: 2 at 29.97,
+    /// 4 at 59.94, and 0 when drop-frame is off or the rate isn't NTSC.
+    pub fn dropped_frames_per_minute(&self) -> u64 {
+        let fps = self.timecode_fps();
+        if self.drop_frame && self.fps_den > 1 && fps.is_multiple_of(30) { fps / 15 } else { 0 }
+    }
+
+    /// `HH:MM:SS:FF` timecode for a frame index, or `HH:MM:SS;FF` in drop-frame.
+    pub fn frame_to_timecode(&self, frame: u64) -> String {
+        let fps = self.timecode_fps();
+        let drop = self.dropped_frames_per_minute();
+        let mut frame = frame;
+        if drop > 0 {
+            // Skip the dropped numbers: every minute except each tenth
+            let frames_per_minute = fps * 60 - drop;
+            let frames_per_ten_minutes = fps * 600 - drop * 9;
+            let tens = frame / frames_per_ten_minutes;
+            let rest = frame % frames_per_ten_minutes;
+            frame += drop * 9 * tens;
+            if rest > drop {
+                frame += drop * ((rest - drop) / frames_per_minute);
+            }
+        }
+        let seconds = frame / fps;
+        let separator = if drop > 0 { ';' } else { ':' };
+        format!("{:02}:{:02}:{:02}{}{:02}", seconds / 3600, (seconds / 60) % 60, seconds % 60, separator, frame % fps)
+    }
+
+    /// Parses an `HH:MM:SS:FF` (or `;FF`) timecode into a frame index. In drop-frame, labels that
+    /// were dropped, like `00:01:00;00`, are rejected, and so are hours too large to count in frames.
+    pub fn timecode_to_frame(&self, timecode: &str) -> Option<u64> {
+        let parts: Vec<u64> = timecode.trim().split([':', ';']).map(|p| p.parse().ok()).collect::<Option<_>>()?;
+        let [hours, minutes, seconds, frames] = parts[..] else { return None };
+        let fps = self.timecode_fps();
+        if minutes >= 60 || seconds >= 60 || frames >= fps {
+            return None;
+        }
+        let drop = self.dropped_frames_per_minute();
+        let total_minutes = hours.checked_mul(60)?.checked_add(minutes)?;
+        if drop > 0 && seconds == 0 && frames < drop && minutes % 10 != 0 {
+            return None;
+        }
+        let frame = total_minutes.checked_mul(60)?.checked_add(seconds)?.checked_mul(fps)?.checked_add(frames)?;
+        Some(frame - drop * (total_minutes - total_minutes / 10))
+    }
+
+    pub fn update_modified_time(&mut self) {
+        self.modified_at = chrono::Utc::now().to_rfc3339();
+    }
+
+    pub fn settings(&self) -> ProjectSettings {
+        ProjectSettings {
+            name: self.name.clone(),
+            fps: self.fps,
+            fps_den: self.fps_den,
+            width: self.width,
+            height: self.height,
+            drop_frame: self.drop_frame,
+            default_still_duration_ms: self.default_still_duration_ms,
         }
-        Err(e) => {
-            println!("engine_load_project_from_json - Deserialization error: {}", e);
-            false
-        },
     }
 }
 
-/// Sets the current file path in the engine. Swift calls this after a successful save/open.
-#[no_mangle]
-pub extern "C" fn engine_set_current_file_path(engine: *mut Engine, file_path: *const c_char) {
-    if engine.is_null() { return; }
-    let eng = unsafe { &mut *engine };
+// --------------------
+// Export
+// --------------------
+impl Project {
+    /// CMX3600 edit decision list for the main track. Gaps produce no event; the next clip simply
+    /// starts later on the record side.
+    pub fn to_edl(&self) -> String {
+        let fcm = if self.dropped_frames_per_minute() > 0 { "DROP FRAME" } else { "NON-DROP FRAME" };
+        let mut edl = format!("TITLE: {}\nFCM: {}\n", self.name, fcm);
+        let Some(track) = self.timeline.tracks.get(MAIN_TRACK) else { return edl };
+        let boundaries = track.boundaries();
+        let mut event = 0;
+        for (idx, clip) in track.clips.iter().enumerate() {
+            if clip.is_gap() {
+                continue;
+            }
+            event += 1;
+            let tc = |ms: u64| self.frame_to_timecode(self.ms_to_frame(ms));
+            edl.push_str(&format!(
+                "\n{:03}  {:<8} {:<5} {:<8} {} {} {} {}\n* FROM CLIP NAME: {}\n",
+                event, "AX", "V", "C",
+                tc(clip.in_point), tc(clip.out_point), tc(boundaries[idx]), tc(boundaries[idx + 1]),
+                clip.name,
+            ));
+        }
+        edl
+    }
 
-    if file_path.is_null() {
-        eng.current_file_path = None;
-    } else {
-        let path = unsafe { CStr::from_ptr(file_path).to_string_lossy().into_owned() };
-        eng.current_file_path = Some(path);
+    /// The main track as a JSON array of clips with absolute timeline positions, for external
+    /// renderers. Source times are `source_in`/`source_out`; `timeline_start`/`timeline_end` are
+    /// summed from the clip durations. Gaps produce no entry.
+    pub fn to_flattened_json(&self) -> String {
+        let Some(track) = self.timeline.tracks.get(MAIN_TRACK) else { return "[]".to_string() };
+        let boundaries = track.boundaries();
+        let clips: Vec<serde_json::Value> = track.clips.iter().enumerate()
+            .filter(|(_, clip)| !clip.is_gap())
+            .map(|(idx, clip)| serde_json::json!({
+                "id": clip.id,
+                "url": clip.url,
+                "source_in": clip.in_point,
+                "source_out": clip.out_point,
+                "timeline_start": boundaries[idx],
+                "timeline_end": boundaries[idx + 1],
+            }))
+            .collect();
+        serde_json::Value::from(clips).to_string()
+    }
+
+    /// Minimal FCPXML 1.9 document with the main track as the spine of a single project.
+    /// Times are frame-aligned rationals, as Final Cut expects.
+    pub fn to_fcpxml(&self) -> String {
+        let fps_den = self.fps_den.max(1) as u64;
+        let time = |ms: u64| format!("{}/{}s", self.ms_to_frame(ms) * fps_den, self.fps);
+        let clips = self.timeline.clips(MAIN_TRACK);
+
+        // One asset per source file, long enough to cover every use of it
+        let mut assets: Vec<(&str, u64)> = Vec::new(); // (url, duration_ms)
+        for clip in clips.iter().filter(|c| !c.is_gap()) {
+            let needed = clip.source_duration_ms.unwrap_or(0).max(clip.out_point);
+            match assets.iter_mut().find(|(url, ..)| *url == clip.url) {
+                Some(asset) => asset.1 = asset.1.max(needed),
+                None => assets.push((&clip.url, needed)),
+            }
+        }
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE fcpxml>\n<fcpxml version=\"1.9\">\n  <resources>\n");
+        xml.push_str(&format!(
+            "    <format id=\"r1\" frameDuration=\"{}/{}s\" width=\"{}\" height=\"{}\"/>\n",
+            fps_den, self.fps, self.width, self.height,
+        ));
+        for (i, (url, duration)) in assets.iter().enumerate() {
+            let src = if url.starts_with('/') { format!("file://{}", url) } else { url.to_string() };
+            xml.push_str(&format!(
+                "    <asset id=\"r{}\" name=\"{}\" src=\"{}\" start=\"0s\" duration=\"{}\" hasVideo=\"1\" hasAudio=\"1\" format=\"r1\"/>\n",
+                i + 2, xml_escape(&name_from_url(url)), xml_escape(&src), time(*duration),
+            ));
+        }
+        let name = xml_escape(&self.name);
+        let total = self.timeline.tracks.get(MAIN_TRACK).map_or(0, |t| t.duration());
+        xml.push_str(&format!("  </resources>\n  <library>\n    <event name=\"{}\">\n      <project name=\"{}\">\n", name, name));
+        let tc_format = if self.dropped_frames_per_minute() > 0 { "DF" } else { "NDF" };
+        xml.push_str(&format!("        <sequence format=\"r1\" duration=\"{}\" tcStart=\"0s\" tcFormat=\"{}\">\n          <spine>\n", time(total), tc_format));
+
+        let mut offset = 0;
+        for clip in clips {
+            let duration = clip.timeline_duration();
+            if clip.is_gap() {
+                xml.push_str(&format!("            <gap name=\"Gap\" offset=\"{}\" duration=\"{}\"/>\n", time(offset), time(duration)));
+            } else {
+                let asset = assets.iter().position(|(url, ..)| *url == clip.url).unwrap_or(0) + 2;
+                xml.push_str(&format!(
+                    "            <asset-clip ref=\"r{}\" name=\"{}\" offset=\"{}\" duration=\"{}\" start=\"{}\"/>\n",
+                    asset, xml_escape(&clip.name), time(offset), time(duration), time(clip.in_point),
+                ));
+            }
+            offset += duration;
+        }
+        xml.push_str("          </spine>\n        </sequence>\n      </project>\n    </event>\n  </library>\n</fcpxml>\n");
+        xml
+    }
+
+    /// How the clips of `newer` differ from this version, keyed by id, as JSON:
+    /// `added` and `removed` hold whole clips; `moved` lists clips whose track or order among the
+    /// clips kept in both versions changed; `modified` holds the old and new form of clips whose
+    /// contents changed.
+    pub fn diff_json(&self, newer: &Project) -> String {
+        let ids = |t: &Timeline| t.all_clips().map(|c| c.id.clone()).collect::<std::collections::HashSet<_>>();
+        let (old_ids, new_ids) = (ids(&self.timeline), ids(&newer.timeline));
+        let kept: std::collections::HashSet<_> = old_ids.intersection(&new_ids).cloned().collect();
+
+        // (track, position among kept clips) of every kept clip on a track
+        let places = |t: &Timeline| {
+            let mut places = std::collections::HashMap::new();
+            for (track_idx, track) in t.tracks.iter().enumerate() {
+                for (position, clip) in track.clips.iter().filter(|c| kept.contains(&c.id)).enumerate() {
+                    places.insert(clip.id.clone(), (track_idx, position));
+                }
+            }
+            places
+        };
+        let (old_places, new_places) = (places(&self.timeline), places(&newer.timeline));
+        let place_json = |place: Option<&(usize, usize)>| place.map(|(track, position)| serde_json::json!({ "track": track, "position": position }));
+
+        let added: Vec<&Clip> = newer.timeline.all_clips().filter(|c| !old_ids.contains(&c.id)).collect();
+        let removed: Vec<&Clip> = self.timeline.all_clips().filter(|c| !new_ids.contains(&c.id)).collect();
+        let mut moved = Vec::new();
+        let mut modified = Vec::new();
+        for clip in newer.timeline.all_clips().filter(|c| kept.contains(&c.id)) {
+            let (old_place, new_place) = (old_places.get(&clip.id), new_places.get(&clip.id));
+            if old_place != new_place {
+                moved.push(serde_json::json!({ "id": clip.id, "from": place_json(old_place), "to": place_json(new_place) }));
+            }
+            if let Some(old) = self.timeline.clip_by_id(&clip.id).filter(|old| *old != clip) {
+                modified.push(serde_json::json!({ "id": clip.id, "old": old, "new": clip }));
+            }
+        }
+        serde_json::json!({ "added": added, "removed": removed, "moved": moved, "modified": modified }).to_string()
     }
 }
 
-/// Marks the current project as saved by clearing the dirty flag.
-#[no_mangle]
-pub extern "C" fn engine_mark_as_saved(engine: *mut Engine) {
-    if engine.is_null() { return; }
-    let eng = unsafe { &mut *engine };
-    eng.is_dirty = false;
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&apos;")
 }
 
-#[no_mangle]
-pub extern "C" fn engine_new_project(engine: *mut Engine, name: *const c_char) -> bool {
-    if engine.is_null() { return false; }
-    
-    let eng = unsafe { &mut *engine };
-    let project_name = if name.is_null() {
-        "Untitled Project".to_string()
-    } else {
-        unsafe { CStr::from_ptr(name).to_string_lossy().into_owned() }
-    };
-    
-    eng.project = Some(Project::new(project_name));
-    eng.current_file_path = None;
-    eng.is_dirty = true;
-    eng.playback_state = PlaybackState::default();
-    true
+// --------------------
+// Commands (from Swift)
+// --------------------
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Command {
+    AddClip(usize, Clip, usize),   // (track, clip, index) insert at index; the clip must be valid
+    InsertClips(usize, Vec<Clip>, usize), // (track, clips, index) insert several clips as one edit, dropping invalid ones
+    InsertClipAtTime(usize, Clip, u64), // (track, clip, time_ms) split there if needed, then insert, rippling later clips
+    OverwriteClipAtTime(usize, Clip, u64), // (track, clip, time_ms) replace whatever the clip covers, without rippling
+    RippleSpace(usize, u64, u64),  // (track, time_ms, duration_ms) open a gap there, pushing later clips back
+    UpsertClip(usize, Clip),       // (track, clip) update url, range and name of the clip with this id, or append it; the clip must be valid
+    RemoveClip(usize, usize),      // (track, index) remove by index
+    RemoveSelected,                // remove every selected clip; handled as RemoveClips of the selection
+    RemoveClips(Vec<String>),      // by id, from every track and the audio lane in one edit
+    ClearTimeline,                 // empty every track, the audio lane, markers and transitions; stop at 0
+    CutClip(usize, usize, u64),    // (track, index, position) cut clip at specified position (ms)
+    SplitAtTime(u64),              // (time_ms) cut the clip under a timeline time on every track
+    UpdateClipRange(usize, usize, u64, u64), // (track, index, in, out) update in/out points of a clip
+    MoveClip(usize, usize, usize), // (track, from, to) move clip from index to index
+    MoveGroup(usize, usize, usize), // (track, index, to) move the clip's group as a block to index `to` among the other clips
+    RemoveGroup(usize, usize),     // (track, index) remove the clip and every clip in its group, on every track
+    GroupSelected,                 // group every selected clip; handled as GroupClips of the selection
+    GroupClips(Vec<String>),       // put the clips with these ids in a new group; needs at least two
+    Ungroup(String),               // by group id
+    DuplicateClip(usize, usize),   // (track, index) insert a copy with a fresh id right after the clip
+    JoinClips(usize, usize),       // (track, index) merge clip at index with the following clip
+    Consolidate(usize),            // (track) join every run of contiguous segments of the same media
+    LiftClip(usize, usize),        // (track, index) replace clip with a gap of the same duration
+    CloseGaps(usize),              // (track) remove every gap, pulling later clips in
+    ReverseTimeline(usize),        // (track) play the clips back to front; each clip still plays forward
+    AddTrack(TrackKind),           // append a new empty track on top
+    RemoveTrack(usize),            // remove a track (the main track can't be removed)
+    AddAudioClip(Clip, u64),       // (clip, start_ms) place audio at an absolute time
+    SetClipVolume(usize, usize, f32), // (track, index, gain) clamped to 0.0..=4.0
+    SetClipSpeed(usize, usize, f32),  // (track, index, rate) must be > 0
+    SlipClip(usize, usize, i64),   // (track, index, delta_ms) shift the source range, keeping the clip's place and length
+    SlideClip(usize, usize, i64),  // (track, index, delta_ms) move the clip along the timeline, trimming its neighbours to match
+    RippleTrimStart(usize, usize, u64), // (track, index, new_in) move the in point, rippling later clips
+    RippleTrimEnd(usize, usize, u64),   // (track, index, new_out) move the out point, rippling later clips
+    SetClipSourceDuration(usize, usize, Option<u64>), // (track, index, duration) None = unknown
+    SetClipName(usize, usize, String), // (track, index, name) display only
+    RelinkClip(usize, usize, String), // (track, index, url) point at different media, keeping the timing
+    SetClipOffline(usize, usize, bool), // (track, index, offline) whether the media file is missing
+    SetClipEnabled(usize, usize, bool), // (track, index, enabled) disabled clips keep their time but show black
+    RelinkByPrefix(String, String), // (old_prefix, new_prefix) rewrite matching urls on every track and the audio lane
+    NormalizeRanges,               // swap in and out points of inverted clips on every track and the audio lane
+    SetClipTransform(usize, usize, Transform), // (track, index, transform) scale must be > 0
+    AddMarker(Marker),             // ids must be unique
+    RemoveMarker(String),          // by marker id
+    MoveMarker(String, u64),       // (marker id, time_ms)
+    SetClipPosterTime(usize, usize, u64), // (track, index, source time) clamped to the clip's range
+    SetClipLabel(usize, usize, u32), // (track, index, rgba) display only; 0 clears
+    SetClipFreeze(usize, usize, bool, u64), // (track, index, freeze, source time) freezing holds that frame, keeping the duration
+    SetClipFades(usize, usize, u64, u64), // (track, index, fade_in_ms, fade_out_ms) each clamped to half the clip
+    AddTransition(usize, usize, u64, TransitionKind), // (track, index, duration_ms, kind) between index and index + 1
+    SetFrameRate(u32, u32),        // (fps, fps_den) both must be > 0
+    SetResolution(u32, u32),       // (width, height) both must be > 0
+    SetDropFrame(bool),            // use drop-frame timecode at NTSC rates
+    SetDefaultStillDuration(u64),  // duration_ms for newly added stills, must be > 0
+    RenameProject(String),         // new project name, must be non-empty; the timeline is untouched
+    SetLoop(bool, Option<(u64, u64)>), // (enabled, range) None range loops the whole timeline
+    SetRate(f32),                  // transport speed, magnitude clamped to 0.1..=8.0; negative plays backward
+    Play,
+    Pause,
+    Stop,                          // pause and return to the start
+    Seek(u64),
+    Tick(u64), // delta_ms
+    TickFrames(u64), // advance by whole frames at the project frame rate
 }
 
-#[no_mangle]
-pub extern "C" fn engine_get_project_name(engine: *const Engine) -> *mut c_char {
-    if engine.is_null() { return std::ptr::null_mut(); }
-    
-    let eng = unsafe { &*engine };
-    if let Some(ref project) = eng.project {
-        CString::new(project.name.clone()).unwrap().into_raw()
-    } else {
-        std::ptr::null_mut()
+impl Command {
+    /// Transport commands move the playhead but never touch the timeline.
+    pub fn is_transport(&self) -> bool {
+        matches!(self, Command::SetLoop(..) | Command::SetRate(_) | Command::Play | Command::Pause | Command::Stop | Command::Seek(_) | Command::Tick(_) | Command::TickFrames(_))
+    }
+
+    /// Settings commands change the project outside the timeline. They aren't undoable, since
+    /// undo snapshots hold only the timeline, and they report `ProjectChanged`.
+    pub fn is_project_setting(&self) -> bool {
+        matches!(self, Command::SetFrameRate(..) | Command::SetResolution(..) | Command::SetDropFrame(_) | Command::SetDefaultStillDuration(_) | Command::RenameProject(_))
+    }
+
+    /// Ticks are driven by the display clock and never dirty the project.
+    pub fn is_tick(&self) -> bool {
+        matches!(self, Command::Tick(_) | Command::TickFrames(_))
     }
 }
 
-#[no_mangle]
-pub extern "C" fn engine_get_current_file_path(engine: *const Engine) -> *mut c_char {
-    if engine.is_null() { return std::ptr::null_mut(); }
-    
-    let eng = unsafe { &*engine };
-    if let Some(ref path) = eng.current_file_path {
-        CString::new(path.clone()).unwrap().into_raw()
-    } else {
-        std::ptr::null_mut()
+// --------------------
+// Playback
+// --------------------
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PlaybackState {
+    pub is_playing: bool,
+    pub time_ms: u64, // Global timeline time
+    pub is_looping: bool,
+    pub loop_range: Option<(u64, u64)>, // (start_ms, end_ms); None loops the whole timeline
+    pub rate: f32, // transport speed multiplier
+}
+
+impl Default for PlaybackState {
+    fn default() -> Self {
+        Self { is_playing: false, time_ms: 0, is_looping: false, loop_range: None, rate: 1.0 }
     }
 }
 
-#[no_mangle]
-pub extern "C" fn engine_has_unsaved_changes(engine: *const Engine) -> bool {
-    if engine.is_null() { return false; }
-    
-    let eng = unsafe { &*engine };
-    eng.is_dirty
+impl PlaybackState {
+    /// Moves the playhead forward. Playback stops at the end, or wraps to the loop start when looping.
+    pub fn advance_to(&mut self, new_time: u64, total_duration: u64) {
+        if self.is_looping {
+            let (start, end) = self.loop_range.unwrap_or((0, total_duration));
+            let end = end.min(total_duration);
+            if end > start && new_time >= end {
+                // Carry the overshoot into the next pass so looping doesn't drift
+                self.time_ms = start + (new_time - end) % (end - start);
+                return;
+            }
+        }
+        if new_time >= total_duration {
+            self.time_ms = total_duration;
+            self.is_playing = false;
+        } else {
+            self.time_ms = new_time;
+        }
+    }
+
+    /// Pulls a playhead left past the end by an edit back to the end, pausing playback there.
+    /// Returns whether it moved.
+    pub fn clamp_to(&mut self, total_duration: u64) -> bool {
+        if self.time_ms > total_duration {
+            self.time_ms = total_duration;
+            self.is_playing = false;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Moves the playhead backward. Playback stops at the start, or wraps to the loop end when looping.
+    pub fn rewind_by(&mut self, delta_ms: u64, total_duration: u64) {
+        if self.is_looping {
+            let (start, end) = self.loop_range.unwrap_or((0, total_duration));
+            let end = end.min(total_duration);
+            if end > start && self.time_ms >= start && self.time_ms < start + delta_ms {
+                let len = (end - start) as i128;
+                let offset = (self.time_ms as i128 - start as i128 - delta_ms as i128).rem_euclid(len);
+                self.time_ms = start + offset as u64;
+                return;
+            }
+        }
+        if delta_ms >= self.time_ms {
+            self.time_ms = 0;
+            self.is_playing = false;
+        } else {
+            self.time_ms -= delta_ms;
+        }
+    }
+}
+
+// Struct to pass playback info over FFI
+#[repr(C)]
+pub struct PlaybackClipInfo {
+    pub id: *mut c_char,
+    pub url: *mut c_char,
+    pub time_in_clip_ms: u64,
+    pub is_offline: bool, // show a placeholder instead of decoding
+    pub is_enabled: bool, // false: show black (or silence) for this clip
+}
+
+// Struct to pass an in-progress transition over FFI
+#[repr(C)]
+pub struct TransitionInfo {
+    pub from_id: *mut c_char,
+    pub from_url: *mut c_char,
+    pub from_time_ms: u64,
+    pub to_id: *mut c_char,
+    pub to_url: *mut c_char,
+    pub to_time_ms: u64,
+    pub progress: f32,
+    pub kind: TransitionKind,
+}
+
+// --------------------
+// Engine (timeline only)
+// --------------------
+pub struct Engine {
+    pub project: Option<Project>,
+    pub current_file_path: Option<String>,
+    pub is_dirty: bool,
+    pub playback_state: PlaybackState,
+    pub undo_stack: Vec<Timeline>, // timeline snapshots taken before each edit
+    pub redo_stack: Vec<Timeline>, // snapshots popped off by undo
+    pub event_callback: EngineEventCallback,
+    pub event_user_data: *mut c_void, // passed back to the callback untouched
+    pub dirty_callback: DirtyCallback,
+    pub dirty_user_data: *mut c_void,
+    pub snap_to_frames: bool, // round cut positions to the project frame grid
+    pub min_clip_duration_ms: Option<u64>, // shortest clip a cut may produce; None = one frame
+    pub clip_offsets: Option<Vec<Vec<u64>>>, // cached `Track::boundaries` per track; None = rebuild needed
+    pub clipboard: Option<Clip>, // last copied clip, independent of the timeline
+    pub load_warnings: Vec<String>, // repairs made while loading the current project
+    pub selected_clip_ids: Vec<String>, // transient, in selection order; never saved
+    pub next_id: u64, // counter behind `gen_id`; stays ahead of every id in the project
+    pub revision: u64, // bumped by every edit, undo, redo and load; playback and rejected edits don't count
+    pub command_log: Option<Vec<Command>>, // commands handled while recording, minus ticks
+    pub suppress_dirty: bool, // inside a batch: edits leave `is_dirty` and `modified_at` alone
+    pub keep_playhead_on_content: bool, // after an edit, move the playhead to follow the frame it was on
+    pub transaction: Option<Transaction>, // engine state to fold into or restore from, while one is open
+    pub solo_clip_id: Option<String>, // transient; playback shows only this clip, from its start
+    pub ripple_mode: bool, // false: removes and shortening trims leave gaps instead of pulling later clips in
+}
+
+/// What `begin_transaction` recorded, so a rollback can put the engine back exactly as it was.
+#[derive(Clone, Debug)]
+pub struct Transaction {
+    pub project_before: Project,
+    pub undo_depth: usize,
+    pub redo_stack: Vec<Timeline>, // the first edit inside clears it
+    pub was_dirty: bool,
+    pub revision: u64,
+}
+
+/// Called with an `EngineEventType` and a JSON payload that is only valid for the duration of the call.
+pub type EngineEventCallback = Option<extern "C" fn(event_type: i32, payload_json: *const c_char, user_data: *mut c_void)>;
+
+/// Called with the new value whenever the project goes from saved to unsaved or back.
+pub type DirtyCallback = Option<extern "C" fn(is_dirty: bool, user_data: *mut c_void)>;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EngineEventType {
+    ClipInserted,
+    ClipRemoved,
+    ClipUpdated,
+    PlaybackChanged,
+    TimelineChanged,
+    ProjectChanged,
+}
+
+/// What a command changed, as specifically as possible so the UI can update incrementally.
+#[derive(Clone, Debug)]
+pub enum EngineEvent {
+    ClipInserted(usize, usize, Clip), // (track, index, clip)
+    ClipRemoved(usize, usize),        // (track, index)
+    ClipUpdated(usize, usize, Clip),  // (track, index, clip)
+    PlaybackChanged(PlaybackState),
+    TimelineChanged(Timeline),        // many clips moved; re-read everything
+    ProjectChanged(ProjectSettings),  // a setting outside the timeline, e.g. the frame rate
+    Unchanged,
+}
+
+impl EngineEvent {
+    /// The FFI type tag and JSON payload for this event, or None for `Unchanged`.
+    pub fn to_ffi(&self) -> Option<(EngineEventType, String)> {
+        let (event_type, payload) = match self {
+            EngineEvent::ClipInserted(track, idx, clip) => {
+                (EngineEventType::ClipInserted, serde_json::json!({ "track": track, "index": idx, "clip": clip }))
+            }
+            EngineEvent::ClipRemoved(track, idx) => {
+                (EngineEventType::ClipRemoved, serde_json::json!({ "track": track, "index": idx }))
+            }
+            EngineEvent::ClipUpdated(track, idx, clip) => {
+                (EngineEventType::ClipUpdated, serde_json::json!({ "track": track, "index": idx, "clip": clip }))
+            }
+            EngineEvent::PlaybackChanged(state) => (EngineEventType::PlaybackChanged, serde_json::json!(state)),
+            EngineEvent::TimelineChanged(timeline) => (EngineEventType::TimelineChanged, serde_json::json!(timeline)),
+            EngineEvent::ProjectChanged(settings) => (EngineEventType::ProjectChanged, serde_json::json!(settings)),
+            EngineEvent::Unchanged => return None,
+        };
+        Some((event_type, payload.to_string()))
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self { 
+            project: Some(Project::new("Untitled Project".to_string())),
+            current_file_path: None,
+            is_dirty: true, // A new project is unsaved.
+            playback_state: PlaybackState::default(),
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            event_callback: None,
+            event_user_data: std::ptr::null_mut(),
+            dirty_callback: None,
+            dirty_user_data: std::ptr::null_mut(),
+            snap_to_frames: false,
+            min_clip_duration_ms: None,
+            clip_offsets: None,
+            clipboard: None,
+            load_warnings: Vec::new(),
+            selected_clip_ids: Vec::new(),
+            next_id: 1,
+            revision: 0,
+            command_log: None,
+            suppress_dirty: false,
+            keep_playhead_on_content: false,
+            transaction: None,
+            solo_clip_id: None,
+            ripple_mode: true,
+        }
+    }
+
+    pub fn get_timeline(&self) -> Timeline {
+        self.project.as_ref().map(|p| p.timeline.clone()).unwrap_or_default()
+    }
+
+    pub fn handle(&mut self, cmd: Command) -> EngineEvent {
+        let cmd = self.resolve(cmd);
+        let changes_timeline = !cmd.is_transport();
+        // Any command but a tick makes the project dirty, unless a batch is open.
+        let dirties = !cmd.is_tick() && self.project.is_some() && !self.suppress_dirty;
+        if let Some(log) = self.command_log.as_mut().filter(|_| !cmd.is_tick()) {
+            log.push(cmd.clone());
+        }
+        let anchor = (changes_timeline && self.keep_playhead_on_content).then(|| self.playhead_anchor()).flatten();
+        let setting = cmd.is_project_setting();
+        let event = self.apply(cmd);
+        // A setting that's already at the requested value leaves the project as it was
+        let dirties = dirties && !(setting && matches!(event, EngineEvent::Unchanged));
+        if dirties {
+            self.set_dirty(true);
+        }
+        if changes_timeline {
+            if !matches!(event, EngineEvent::Unchanged) {
+                self.revision += 1;
+            }
+            self.timeline_changed();
+        }
+        if let Some((clip_id, source_ms)) = anchor {
+            self.restore_playhead_anchor(&clip_id, source_ms);
+        }
+        if self.transaction.is_none() {
+            self.emit(&event); // An open transaction reports once, on commit
+        }
+        event
+    }
+
+    // Turns a command that depends on engine settings or the selection into the explicit edit it
+    // stands for, so the command log replays the same edit on an engine in a different state.
+    fn resolve(&self, cmd: Command) -> Command {
+        match cmd {
+            // Without ripple a removed clip leaves a gap
+            Command::RemoveClip(track, idx) if !self.ripple_mode => Command::LiftClip(track, idx),
+            Command::RemoveSelected => Command::RemoveClips(self.selected_clip_ids.clone()),
+            Command::GroupSelected => Command::GroupClips(self.selected_clip_ids.clone()),
+            cmd => cmd,
+        }
+    }
+
+    // The main-track clip under the playhead and the source time showing in it.
+    fn playhead_anchor(&self) -> Option<(String, u64)> {
+        let idx = self.clip_index_at(MAIN_TRACK, self.playback_state.time_ms)?;
+        let clip = &self.project.as_ref()?.timeline.clips(MAIN_TRACK)[idx];
+        let offset = self.playback_state.time_ms - self.track_offsets(MAIN_TRACK)[idx];
+        Some((clip.id.clone(), clip.source_time_at(offset)))
+    }
+
+    // Puts the playhead back on a source time in a main-track clip, wherever the clip now is.
+    // Leaves it alone if the clip was removed.
+    fn restore_playhead_anchor(&mut self, clip_id: &str, source_ms: u64) {
+        let Some(project) = self.project.as_ref() else { return };
+        let Some(idx) = project.timeline.clips(MAIN_TRACK).iter().position(|c| c.id == clip_id) else { return };
+        let offset = project.timeline.clips(MAIN_TRACK)[idx].offset_of_source_time(source_ms);
+        self.playback_state.time_ms = self.track_offsets(MAIN_TRACK)[idx] + offset;
+    }
+
+    /// Brings derived state back in step after the timeline may have changed: rebuilds the
+    /// offset cache, drops selected or soloed clips that no longer exist and keeps the playhead
+    /// inside the timeline.
+    pub fn timeline_changed(&mut self) {
+        self.rebuild_clip_offsets();
+        self.clamp_playhead();
+        let timeline = self.project.as_ref().map(|p| &p.timeline);
+        let exists = |id: &String| timeline.is_some_and(|t| t.clip_by_id(id).is_some());
+        self.selected_clip_ids.retain(exists);
+        self.solo_clip_id = self.solo_clip_id.take().filter(exists);
+    }
+
+    /// Moves a playhead that is past the end of the timeline back to the end and pauses. Returns
+    /// whether it moved.
+    pub fn clamp_playhead(&mut self) -> bool {
+        let total_duration = self.project.as_ref().map_or(0, |p| p.timeline.total_duration());
+        self.playback_state.clamp_to(total_duration)
+    }
+
+    /// Recomputes the cached clip offsets. Call after anything that may change clip durations.
+    pub fn rebuild_clip_offsets(&mut self) {
+        self.clip_offsets = self.project.as_ref().map(|p| p.timeline.tracks.iter().map(Track::boundaries).collect());
+    }
+
+    // The cached offsets for a track, or None if the cache is missing or out of step with the timeline.
+    fn cached_offsets(&self, track: usize) -> Option<&[u64]> {
+        let project = self.project.as_ref()?;
+        let offsets = self.clip_offsets.as_ref().filter(|o| o.len() == project.timeline.tracks.len())?;
+        let offsets = &offsets[track];
+        (offsets.len() == project.timeline.tracks[track].clips.len() + 1).then_some(offsets.as_slice())
+    }
+
+    /// Updates the dirty flag, telling the dirty callback only when it actually flips.
+    pub fn set_dirty(&mut self, dirty: bool) {
+        if self.is_dirty != dirty {
+            self.is_dirty = dirty;
+            if let Some(callback) = self.dirty_callback {
+                callback(dirty, self.dirty_user_data);
+            }
+        }
+    }
+
+    /// Forwards an event to the registered FFI callback, if any.
+    pub fn emit(&self, event: &EngineEvent) {
+        if let Some(callback) = self.event_callback {
+            if let Some((event_type, payload)) = event.to_ffi() {
+                let payload = CString::new(payload).unwrap();
+                callback(event_type as i32, payload.as_ptr(), self.event_user_data);
+            }
+        }
+    }
+
+    fn apply(&mut self, cmd: Command) -> EngineEvent {
+        if let Some(ref mut project) = self.project {
+            let playback_before = self.playback_state.clone();
+            let snapshot = (!cmd.is_transport()).then(|| project.timeline.clone());
+            let settings_before = cmd.is_project_setting().then(|| project.settings());
+            // Without ripple whatever a trim cuts off leaves a gap
+            let trimmed = match &cmd {
+                Command::UpdateClipRange(track, idx, ..) | Command::RippleTrimStart(track, idx, _) | Command::RippleTrimEnd(track, idx, _)
+                    if !self.ripple_mode =>
+                {
+                    project.timeline.clips(*track).get(*idx).map(|c| (*track, *idx, c.timeline_duration(), c.in_point))
+                }
+                _ => None,
+            };
+            // Caller ids may already look like `gen_id` output; keep the counter ahead of them
+            let incoming: &[Clip] = match &cmd {
+                Command::AddClip(_, clip, _)
+                | Command::UpsertClip(_, clip)
+                | Command::InsertClipAtTime(_, clip, _)
+                | Command::OverwriteClipAtTime(_, clip, _)
+                | Command::AddAudioClip(clip, _) => std::slice::from_ref(clip),
+                Command::InsertClips(_, clips, _) => clips,
+                _ => &[],
+            };
+            for clip in incoming {
+                reserve_id(&mut self.next_id, &clip.id);
+            }
+            match &cmd {
+                Command::AddClip(track, clip, idx) => {
+                    if let Some(clips) = project.timeline.clips_mut(*track).filter(|_| clip.is_valid()) {
+                        if *idx <= clips.len() {
+                            clips.insert(*idx, clip.clone());
+                        } else {
+                            clips.push(clip.clone());
+                        }
+                    }
+                }
+                Command::InsertClips(track, new_clips, idx) => {
+                    if let Some(clips) = project.timeline.clips_mut(*track) {
+                        let idx = (*idx).min(clips.len());
+                        // Bad clips are dropped rather than failing the whole batch
+                        clips.splice(idx..idx, new_clips.iter().filter(|c| c.is_valid()).cloned());
+                    }
+                }
+                Command::ClearTimeline => {
+                    // Tracks stay so the layout survives; only their contents go
+                    let timeline = &mut project.timeline;
+                    for track in &mut timeline.tracks {
+                        track.clips.clear();
+                    }
+                    timeline.audio_clips.clear();
+                    timeline.markers.clear();
+                    timeline.transitions.clear();
+                    self.playback_state.is_playing = false;
+                    self.playback_state.time_ms = 0;
+                }
+                Command::RemoveClips(ids) => {
+                    // Filter by id; indices shift as clips go
+                    for track in &mut project.timeline.tracks {
+                        track.clips.retain(|c| !ids.contains(&c.id));
+                    }
+                    project.timeline.audio_clips.retain(|a| !ids.contains(&a.clip.id));
+                }
+                Command::InsertClipAtTime(track, clip, time_ms) => {
+                    if let Some(track) = project.timeline.tracks.get_mut(*track).filter(|_| clip.is_valid()) {
+                        let idx = track.split_at(*time_ms, &mut self.next_id);
+                        track.clips.insert(idx, clip.clone());
+                    }
+                }
+                Command::OverwriteClipAtTime(track, clip, time_ms) => {
+                    if let Some(track) = project.timeline.tracks.get_mut(*track).filter(|_| clip.is_valid()) {
+                        let track_end = track.duration();
+                        if *time_ms > track_end {
+                            // Pad with a gap so the clip lands at the requested time
+                            let gap_id = gen_id(&mut self.next_id, "gap");
+                            track.clips.push(Clip::gap(gap_id, *time_ms - track_end));
+                        }
+                        // Cut at both edges, then swap out everything in between
+                        let start = track.split_at(*time_ms, &mut self.next_id);
+                        let end = track.split_at(*time_ms + clip.timeline_duration(), &mut self.next_id);
+                        track.clips.splice(start..end, [clip.clone()]);
+                    }
+                }
+                Command::RippleSpace(track, time_ms, duration_ms) => {
+                    if let Some(track) = project.timeline.tracks.get_mut(*track) {
+                        // Nothing to push when the time is at or past the end
+                        if *duration_ms > 0 && *time_ms < track.duration() {
+                            let idx = track.split_at(*time_ms, &mut self.next_id);
+                            track.clips.insert(idx, Clip::gap(gen_id(&mut self.next_id, "gap"), *duration_ms));
+                        }
+                    }
+                }
+                Command::UpsertClip(track, clip) => {
+                    if let Some(clips) = project.timeline.clips_mut(*track).filter(|_| clip.is_valid()) {
+                        match clips.iter_mut().find(|c| c.id == clip.id) {
+                            Some(existing) => {
+                                // New media brings its own name; the same media keeps any rename
+                                if existing.url != clip.url {
+                                    existing.name = clip.name.clone();
+                                }
+                                existing.url = clip.url.clone();
+                                existing.in_point = clip.in_point;
+                                existing.out_point = clip.out_point;
+                            }
+                            None => clips.push(clip.clone()),
+                        }
+                    }
+                }
+                Command::RemoveClip(track, idx) => {
+                    if let Some(clips) = project.timeline.clips_mut(*track) {
+                        if *idx < clips.len() {
+                            clips.remove(*idx);
+                        }
+                    }
+                }
+                Command::CutClip(track, idx, position) => {
+                    let position = if self.snap_to_frames { project.snap_to_frame(*position) } else { *position };
+                    let min_duration = self.min_clip_duration_ms.unwrap_or_else(|| project.frame_to_ms(1)).max(1);
+                    if let Some(track) = project.timeline.tracks.get_mut(*track) {
+                        track.cut_clip(*idx, position, min_duration, &mut self.next_id);
+                    }
+                }
+                Command::SplitAtTime(time_ms) => {
+                    let time_ms = if self.snap_to_frames { project.snap_to_frame(*time_ms) } else { *time_ms };
+                    let min_duration = self.min_clip_duration_ms.unwrap_or_else(|| project.frame_to_ms(1)).max(1);
+                    for track in &mut project.timeline.tracks {
+                        let boundaries = track.boundaries();
+                        // A time on a boundary already has a cut there
+                        let idx = boundaries.partition_point(|&b| b <= time_ms).saturating_sub(1);
+                        if idx < track.clips.len() && boundaries[idx] != time_ms {
+                            let position = track.cut_position(idx, time_ms - boundaries[idx]);
+                            track.cut_clip(idx, position, min_duration, &mut self.next_id);
+                        }
+                    }
+                }
+                Command::UpdateClipRange(track, idx, in_point, out_point) => {
+                    if let Some(clip) = project.timeline.clips_mut(*track).and_then(|c| c.get_mut(*idx)) {
+                        let out_point = clip.clamp_to_source(*out_point);
+                        // Only update if the new range is valid
+                        if *in_point < out_point {
+                            clip.in_point = *in_point;
+                            clip.out_point = out_point;
+                        }
+                    }
+                }
+                Command::MoveClip(track, from, to) => {
+                    if let Some(clips) = project.timeline.clips_mut(*track) {
+                        if *from < clips.len() && from != to {
+                            let clip = clips.remove(*from);
+                            let to = (*to).min(clips.len());
+                            clips.insert(to, clip);
+                        }
+                    }
+                }
+                Command::MoveGroup(track, idx, to) => {
+                    if let Some(clips) = project.timeline.clips_mut(*track) {
+                        if let Some(group_id) = clips.get(*idx).map(|c| c.group_id.clone()) {
+                            // An ungrouped clip is a group of one
+                            let (block, mut rest): (Vec<Clip>, Vec<Clip>) = match group_id {
+                                Some(group_id) => clips.drain(..).partition(|c| c.group_id.as_ref() == Some(&group_id)),
+                                None => (vec![clips.remove(*idx)], std::mem::take(clips)),
+                            };
+                            let to = (*to).min(rest.len());
+                            rest.splice(to..to, block);
+                            *clips = rest;
+                        }
+                    }
+                }
+                Command::RemoveGroup(track, idx) => {
+                    if let Some(clip) = project.timeline.clips(*track).get(*idx) {
+                        match clip.group_id.clone() {
+                            Some(group_id) => {
+                                let in_group = |c: &Clip| c.group_id.as_ref() == Some(&group_id);
+                                for track in &mut project.timeline.tracks {
+                                    track.clips.retain(|c| !in_group(c));
+                                }
+                                project.timeline.audio_clips.retain(|a| !in_group(&a.clip));
+                            }
+                            None => {
+                                project.timeline.tracks[*track].clips.remove(*idx);
+                            }
+                        }
+                    }
+                }
+                Command::GroupClips(ids) => {
+                    if ids.len() >= 2 {
+                        let group_id = gen_id(&mut self.next_id, "group");
+                        for clip in project.timeline.all_clips_mut().filter(|c| ids.contains(&c.id)) {
+                            clip.group_id = Some(group_id.clone());
+                        }
+                    }
+                }
+                Command::RemoveSelected | Command::GroupSelected => {} // `handle` resolves these first
+                Command::Ungroup(group_id) => {
+                    for clip in project.timeline.all_clips_mut().filter(|c| c.group_id.as_ref() == Some(group_id)) {
+                        clip.group_id = None;
+                    }
+                }
+                Command::DuplicateClip(track, idx) => {
+                    if let Some(clips) = project.timeline.clips_mut(*track) {
+                        if let Some(original) = clips.get(*idx) {
+                            let mut copy = original.clone();
+                            copy.id = gen_id(&mut self.next_id, &original.id);
+                            clips.insert(*idx + 1, copy);
+                        }
+                    }
+                }
+                Command::JoinClips(track, idx) => {
+                    if let Some(clips) = project.timeline.clips_mut(*track) {
+                        if *idx + 1 < clips.len() {
+                            // Only join contiguous segments of the same media
+                            if clips[*idx].joins_with(&clips[*idx + 1]) {
+                                let second = clips.remove(*idx + 1);
+                                clips[*idx].out_point = second.out_point;
+                            }
+                        }
+                    }
+                }
+                Command::Consolidate(track) => {
+                    if let Some(clips) = project.timeline.clips_mut(*track) {
+                        // Same rule as JoinClips, applied to each neighbouring pair in turn
+                        clips.dedup_by(|second, first| {
+                            let joins = first.joins_with(second);
+                            if joins {
+                                first.out_point = second.out_point;
+                            }
+                            joins
+                        });
+                    }
+                }
+                Command::LiftClip(track, idx) => {
+                    if let Some(clip) = project.timeline.clips_mut(*track).and_then(|c| c.get_mut(*idx)) {
+                        if !clip.is_gap() {
+                            *clip = Clip::gap(gen_id(&mut self.next_id, "gap"), clip.timeline_duration());
+                        }
+                    }
+                }
+                Command::CloseGaps(track) => {
+                    if let Some(clips) = project.timeline.clips_mut(*track) {
+                        clips.retain(|c| !c.is_gap());
+                    }
+                }
+                Command::ReverseTimeline(track) => {
+                    let timeline = &mut project.timeline;
+                    if let Some(track) = timeline.tracks.get_mut(*track) {
+                        track.clips.reverse();
+                        // A transition now runs the other way across the same cut
+                        for transition in &mut timeline.transitions {
+                            if track.clips.iter().any(|c| c.id == transition.from_clip_id) {
+                                std::mem::swap(&mut transition.from_clip_id, &mut transition.to_clip_id);
+                            }
+                        }
+                    }
+                }
+                Command::AddTrack(kind) => {
+                    let id = gen_id(&mut self.next_id, "track");
+                    project.timeline.tracks.push(Track::new(id, *kind));
+                }
+                Command::RemoveTrack(track) => {
+                    if *track != MAIN_TRACK && *track < project.timeline.tracks.len() {
+                        project.timeline.tracks.remove(*track);
+                    }
+                }
+                Command::AddAudioClip(clip, start_ms) => {
+                    if clip.is_valid() {
+                        project.timeline.audio_clips.push(AudioClip { start_ms: *start_ms, clip: clip.clone() });
+                    }
+                }
+                Command::SetClipVolume(track, idx, volume) => {
+                    if let Some(clip) = project.timeline.clips_mut(*track).and_then(|c| c.get_mut(*idx)) {
+                        if !volume.is_nan() {
+                            clip.volume = volume.clamp(0.0, 4.0);
+                        }
+                    }
+                }
+                Command::SetClipSpeed(track, idx, speed) => {
+                    if let Some(clip) = project.timeline.clips_mut(*track).and_then(|c| c.get_mut(*idx)) {
+                        if speed.is_finite() && *speed > 0.0 {
+                            clip.speed = *speed;
+                        }
+                    }
+                }
+                Command::SetResolution(width, height) => {
+                    if *width > 0 && *height > 0 {
+                        project.width = *width;
+                        project.height = *height;
+                    }
+                }
+                Command::SetDefaultStillDuration(duration_ms) => {
+                    if *duration_ms > 0 {
+                        project.default_still_duration_ms = *duration_ms;
+                    }
+                }
+                Command::RenameProject(name) => {
+                    if !name.is_empty() {
+                        project.name = name.clone();
+                    }
+                }
+                Command::SetDropFrame(drop_frame) => {
+                    project.drop_frame = *drop_frame;
+                }
+                Command::SetLoop(enabled, range) => {
+                    self.playback_state.is_looping = *enabled;
+                    self.playback_state.loop_range = *range;
+                }
+                Command::SetRate(rate) => {
+                    if rate.is_finite() && *rate != 0.0 {
+                        self.playback_state.rate = rate.signum() * rate.abs().clamp(0.1, 8.0);
+                    }
+                }
+                Command::Play => self.playback_state.is_playing = true,
+                Command::Pause => self.playback_state.is_playing = false,
+                Command::Stop => {
+                    self.playback_state.is_playing = false;
+                    self.playback_state.time_ms = 0;
+                }
+                Command::Seek(time) => {
+                    self.playback_state.time_ms = (*time).min(project.timeline.total_duration());
+                },
+                Command::SlipClip(track, idx, delta_ms) => {
+                    if let Some(clip) = project.timeline.clips_mut(*track).and_then(|c| c.get_mut(*idx)) {
+                        if !clip.is_gap() {
+                            // Stop at the start of the media, and at its end when that's known
+                            let earliest = -(clip.in_point as i128);
+                            let latest = clip.source_duration_ms.map_or(i128::MAX, |d| (d as i128 - clip.out_point as i128).max(0));
+                            let delta = (*delta_ms as i128).clamp(earliest, latest) as i64;
+                            clip.in_point = clip.in_point.saturating_add_signed(delta);
+                            clip.out_point = clip.out_point.saturating_add_signed(delta);
+                        }
+                    }
+                }
+                Command::SlideClip(track, idx, delta_ms) => {
+                    if let Some(clips) = project.timeline.clips_mut(*track) {
+                        if *idx > 0 && *idx + 1 < clips.len() {
+                            // The previous clip's end and the next clip's start follow the slide, in their own source time
+                            let source_delta = |c: &Clip| (*delta_ms as f64 * c.effective_speed()).round() as i128;
+                            let (prev, next) = (&clips[*idx - 1], &clips[*idx + 1]);
+                            let prev_out = prev.out_point as i128 + source_delta(prev);
+                            let next_in = next.in_point as i128 + source_delta(next);
+                            let prev_fits = prev_out > prev.in_point as i128 && prev.source_duration_ms.is_none_or(|d| prev_out <= d as i128);
+                            let next_fits = next_in >= 0 && next_in < next.out_point as i128;
+                            if prev_fits && next_fits {
+                                clips[*idx - 1].out_point = prev_out as u64;
+                                clips[*idx + 1].in_point = next_in as u64;
+                            }
+                        }
+                    }
+                }
+                Command::RippleTrimStart(track, idx, new_in) => {
+                    if let Some(clip) = project.timeline.clips_mut(*track).and_then(|c| c.get_mut(*idx)) {
+                        if *new_in < clip.out_point {
+                            clip.in_point = *new_in;
+                        }
+                    }
+                }
+                Command::RippleTrimEnd(track, idx, new_out) => {
+                    if let Some(clip) = project.timeline.clips_mut(*track).and_then(|c| c.get_mut(*idx)) {
+                        let new_out = clip.clamp_to_source(*new_out);
+                        if new_out > clip.in_point {
+                            clip.out_point = new_out;
+                        }
+                    }
+                }
+                Command::SetClipSourceDuration(track, idx, duration) => {
+                    if let Some(clip) = project.timeline.clips_mut(*track).and_then(|c| c.get_mut(*idx)) {
+                        clip.source_duration_ms = *duration;
+                        // Pull an out point that runs past the media back in, if that leaves a valid range
+                        let out_point = clip.clamp_to_source(clip.out_point);
+                        if out_point > clip.in_point {
+                            clip.out_point = out_point;
+                        }
+                    }
+                }
+                Command::SetClipName(track, idx, name) => {
+                    if let Some(clip) = project.timeline.clips_mut(*track).and_then(|c| c.get_mut(*idx)) {
+                        clip.name = name.clone();
+                    }
+                }
+                Command::RelinkClip(track, idx, url) => {
+                    if let Some(clip) = project.timeline.clips_mut(*track).and_then(|c| c.get_mut(*idx)) {
+                        if !url.is_empty() {
+                            clip.url = url.clone();
+                        }
+                    }
+                }
+                Command::SetClipOffline(track, idx, offline) => {
+                    if let Some(clip) = project.timeline.clips_mut(*track).and_then(|c| c.get_mut(*idx)) {
+                        clip.is_offline = *offline;
+                    }
+                }
+                Command::SetClipEnabled(track, idx, enabled) => {
+                    if let Some(clip) = project.timeline.clips_mut(*track).and_then(|c| c.get_mut(*idx)) {
+                        clip.enabled = *enabled;
+                    }
+                }
+                Command::RelinkByPrefix(old_prefix, new_prefix) => {
+                    if !old_prefix.is_empty() {
+                        for clip in project.timeline.all_clips_mut() {
+                            if let Some(rest) = clip.url.strip_prefix(old_prefix.as_str()) {
+                                clip.url = format!("{}{}", new_prefix, rest);
+                            }
+                        }
+                    }
+                }
+                Command::NormalizeRanges => {
+                    for clip in project.timeline.all_clips_mut() {
+                        if clip.out_point < clip.in_point {
+                            std::mem::swap(&mut clip.in_point, &mut clip.out_point);
+                        }
+                    }
+                }
+                Command::SetClipTransform(track, idx, transform) => {
+                    if let Some(clip) = project.timeline.clips_mut(*track).and_then(|c| c.get_mut(*idx)) {
+                        if transform.is_valid() {
+                            clip.transform = *transform;
+                        }
+                    }
+                }
+                Command::SetClipFreeze(track, idx, freeze, frame_time) => {
+                    if let Some(clip) = project.timeline.clips_mut(*track).and_then(|c| c.get_mut(*idx)) {
+                        // The range is left alone, so unfreezing plays the clip as it was
+                        clip.freeze_frame_ms = freeze.then(|| clip.clamp_to_source(frame_time.saturating_add(1)).saturating_sub(1));
+                        clip.freeze = *freeze;
+                    }
+                }
+                Command::SetClipLabel(track, idx, color) => {
+                    if let Some(clip) = project.timeline.clips_mut(*track).and_then(|c| c.get_mut(*idx)) {
+                        clip.label_color = *color;
+                    }
+                }
+                Command::SetClipPosterTime(track, idx, time) => {
+                    if let Some(clip) = project.timeline.clips_mut(*track).and_then(|c| c.get_mut(*idx)) {
+                        clip.poster_time_ms = (*time).clamp(clip.in_point, clip.out_point);
+                    }
+                }
+                Command::SetClipFades(track, idx, fade_in, fade_out) => {
+                    if let Some(clip) = project.timeline.clips_mut(*track).and_then(|c| c.get_mut(*idx)) {
+                        let half = clip.timeline_duration() / 2;
+                        clip.fade_in_ms = (*fade_in).min(half);
+                        clip.fade_out_ms = (*fade_out).min(half);
+                    }
+                }
+                Command::AddTransition(track, idx, duration_ms, kind) => {
+                    let clips = project.timeline.clips(*track);
+                    if let (Some(from), Some(to)) = (clips.get(*idx), clips.get(*idx + 1)) {
+                        // Both clips must be long enough to cover the transition, and the incoming
+                        // clip needs media before its in point for the lead-in
+                        let fits = *duration_ms > 0 && from.timeline_duration() >= *duration_ms && to.timeline_duration() >= *duration_ms
+                            && to.lead_in_source_ms(*duration_ms) <= to.in_point;
+                        if fits {
+                            let transition = Transition {
+                                from_clip_id: from.id.clone(),
+                                to_clip_id: to.id.clone(),
+                                duration_ms: *duration_ms,
+                                kind: *kind,
+                            };
+                            project.timeline.transitions.retain(|t| t.from_clip_id != transition.from_clip_id || t.to_clip_id != transition.to_clip_id);
+                            project.timeline.transitions.push(transition);
+                        }
+                    }
+                }
+                Command::AddMarker(marker) => {
+                    if !project.timeline.markers.iter().any(|m| m.id == marker.id) {
+                        project.timeline.insert_marker(marker.clone());
+                    }
+                }
+                Command::RemoveMarker(id) => {
+                    project.timeline.markers.retain(|m| m.id != *id);
+                }
+                Command::MoveMarker(id, time_ms) => {
+                    if let Some(idx) = project.timeline.markers.iter().position(|m| m.id == *id) {
+                        let mut marker = project.timeline.markers.remove(idx);
+                        marker.time_ms = *time_ms;
+                        project.timeline.insert_marker(marker);
+                    }
+                }
+                Command::SetFrameRate(fps, fps_den) => {
+                    if *fps > 0 && *fps_den > 0 {
+                        project.fps = *fps;
+                        project.fps_den = *fps_den;
+                    }
+                }
+                Command::Tick(delta_ms) => {
+                    if self.playback_state.is_playing {
+                        let rate = self.playback_state.rate as f64;
+                        let scaled_delta = (*delta_ms as f64 * rate.abs()).round() as u64;
+                        let total_duration = project.timeline.total_duration();
+                        if rate < 0.0 {
+                            self.playback_state.rewind_by(scaled_delta, total_duration);
+                        } else {
+                            let new_time = self.playback_state.time_ms + scaled_delta;
+                            self.playback_state.advance_to(new_time, total_duration);
+                        }
+                    }
+                }
+                Command::TickFrames(frames) => {
+                    if self.playback_state.is_playing {
+                        let frame = project.ms_to_frame(self.playback_state.time_ms) + *frames;
+                        self.playback_state.advance_to(project.frame_to_ms(frame), project.timeline.total_duration());
+                    }
+                }
+            }
+            let mut gap_left = false;
+            if let Some((track, idx, duration_before, in_before)) = trimmed {
+                let clips = &mut project.timeline.tracks[track].clips;
+                let clip = &clips[idx];
+                let lost = duration_before.saturating_sub(clip.timeline_duration());
+                if lost > 0 {
+                    // Time cut from the head goes before the clip and the rest after it, so every
+                    // remaining frame stays where it was
+                    let head_source = clip.in_point.saturating_sub(in_before);
+                    let head = ((head_source as f64 / clip.effective_speed()).round() as u64).min(lost);
+                    if lost > head {
+                        clips.insert(idx + 1, Clip::gap(gen_id(&mut self.next_id, "gap"), lost - head));
+                    }
+                    if head > 0 {
+                        clips.insert(idx, Clip::gap(gen_id(&mut self.next_id, "gap"), head));
+                    }
+                    gap_left = true;
+                }
+            }
+            // Only commands that actually changed the timeline are worth undoing.
+            let changed = match snapshot {
+                Some(snapshot) if snapshot != project.timeline => {
+                    self.undo_stack.push(snapshot);
+                    self.redo_stack.clear();
+                    true
+                }
+                _ => false,
+            };
+            if !cmd.is_tick() && !self.suppress_dirty {
+                project.update_modified_time();
+            }
+            if let Some(settings_before) = settings_before {
+                let settings = project.settings();
+                if settings != settings_before {
+                    EngineEvent::ProjectChanged(settings)
+                } else {
+                    EngineEvent::Unchanged
+                }
+            } else if cmd.is_transport() {
+                if self.playback_state != playback_before {
+                    EngineEvent::PlaybackChanged(self.playback_state.clone())
+                } else {
+                    EngineEvent::Unchanged
+                }
+            } else if changed && !gap_left {
+                Self::edit_event(&cmd, &project.timeline)
+            } else if changed {
+                EngineEvent::TimelineChanged(project.timeline.clone())
+            } else {
+                EngineEvent::Unchanged
+            }
+        } else {
+            EngineEvent::Unchanged
+        }
+    }
+
+    // Describes an edit that changed the timeline. Commands touching a single clip report just
+    // that clip; anything that restructures the timeline falls back to TimelineChanged.
+    fn edit_event(cmd: &Command, timeline: &Timeline) -> EngineEvent {
+        match cmd {
+            Command::AddClip(track, _, idx) => {
+                let clips = timeline.clips(*track);
+                let idx = (*idx).min(clips.len() - 1); // past-the-end inserts were appended
+                EngineEvent::ClipInserted(*track, idx, clips[idx].clone())
+            }
+            Command::DuplicateClip(track, idx) => EngineEvent::ClipInserted(*track, *idx + 1, timeline.clips(*track)[*idx + 1].clone()),
+            Command::RemoveClip(track, idx) => EngineEvent::ClipRemoved(*track, *idx),
+            Command::UpdateClipRange(track, idx, ..)
+            | Command::LiftClip(track, idx)
+            | Command::SetClipVolume(track, idx, _)
+            | Command::SetClipSpeed(track, idx, _)
+            | Command::SlipClip(track, idx, _)
+            | Command::RippleTrimStart(track, idx, _)
+            | Command::RippleTrimEnd(track, idx, _)
+            | Command::SetClipSourceDuration(track, idx, _)
+            | Command::SetClipName(track, idx, _)
+            | Command::RelinkClip(track, idx, _)
+            | Command::SetClipOffline(track, idx, _)
+            | Command::SetClipEnabled(track, idx, _)
+            | Command::SetClipTransform(track, idx, _)
+            | Command::SetClipFades(track, idx, ..)
+            | Command::SetClipPosterTime(track, idx, _)
+            | Command::SetClipLabel(track, idx, _)
+            | Command::SetClipFreeze(track, idx, ..) => {
+                EngineEvent::ClipUpdated(*track, *idx, timeline.clips(*track)[*idx].clone())
+            }
+            _ => EngineEvent::TimelineChanged(timeline.clone()),
+        }
+    }
+
+    /// Moves the playhead to the next edit point after the current time. Returns the new time.
+    pub fn seek_to_next_edit(&mut self) -> u64 {
+        let time = self.playback_state.time_ms;
+        let track_count = self.project.as_ref().map_or(0, |p| p.timeline.tracks.len());
+        // Each track's boundaries are sorted, so the nearest edit is the earliest per-track candidate
+        let next = (0..track_count)
+            .filter_map(|track| {
+                let offsets = self.track_offsets(track);
+                offsets.get(offsets.partition_point(|&b| b <= time)).copied()
+            })
+            .min();
+        if let Some(next) = next {
+            self.handle(Command::Seek(next));
+        }
+        self.playback_state.time_ms
+    }
+
+    /// Moves the playhead to the previous edit point before the current time. Returns the new time.
+    pub fn seek_to_prev_edit(&mut self) -> u64 {
+        let time = self.playback_state.time_ms;
+        let track_count = self.project.as_ref().map_or(0, |p| p.timeline.tracks.len());
+        let prev = (0..track_count)
+            .filter_map(|track| {
+                let offsets = self.track_offsets(track);
+                offsets.partition_point(|&b| b < time).checked_sub(1).map(|i| offsets[i])
+            })
+            .max();
+        if let Some(prev) = prev {
+            self.handle(Command::Seek(prev));
+        }
+        self.playback_state.time_ms
+    }
+
+    /// The clip boundary or marker nearest to `time_ms`, if one is within `tolerance_ms` (inclusive);
+    /// otherwise `time_ms` itself. The earlier target wins a tie.
+    pub fn snap_time(&self, time_ms: u64, tolerance_ms: u64) -> u64 {
+        let Some(project) = self.project.as_ref() else { return time_ms };
+        let mut candidates: Vec<u64> = project.timeline.markers.iter().map(|m| m.time_ms).collect();
+        for track in 0..project.timeline.tracks.len() {
+            // Only the boundaries on either side of the time can be nearest
+            let offsets = self.track_offsets(track);
+            let idx = offsets.partition_point(|&b| b < time_ms);
+            candidates.extend(&offsets[idx.saturating_sub(1)..(idx + 1).min(offsets.len())]);
+        }
+        candidates.into_iter()
+            .filter(|t| t.abs_diff(time_ms) <= tolerance_ms)
+            .min_by_key(|&t| (t.abs_diff(time_ms), t))
+            .unwrap_or(time_ms)
+    }
+
+    // Clip offsets for a track, from the cache when it's valid.
+    fn track_offsets(&self, track: usize) -> std::borrow::Cow<'_, [u64]> {
+        match self.cached_offsets(track) {
+            Some(offsets) => std::borrow::Cow::Borrowed(offsets),
+            None => std::borrow::Cow::Owned(self.project.as_ref().map(|p| p.timeline.tracks[track].boundaries()).unwrap_or_default()),
+        }
+    }
+
+    /// Stores a copy of the clip in the clipboard. Returns false if there is no such clip.
+    pub fn copy_clip(&mut self, track: usize, idx: usize) -> bool {
+        let clip = self.project.as_ref().and_then(|p| p.timeline.clips(track).get(idx).cloned());
+        if clip.is_some() {
+            self.clipboard = clip;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Inserts the clipboard clip at `idx` under a fresh id. Returns false if the clipboard is
+    /// empty or holds a gap.
+    pub fn paste_clip(&mut self, track: usize, idx: usize) -> bool {
+        let Some(mut clip) = self.clipboard.clone() else { return false };
+        clip.id = self.gen_id(&clip.id);
+        matches!(self.handle(Command::AddClip(track, clip, idx)), EngineEvent::ClipInserted(..))
+    }
+
+    /// A new id unique within this engine, derived from `base` so it stays readable.
+    pub fn gen_id(&mut self, base: &str) -> String {
+        gen_id(&mut self.next_id, base)
+    }
+
+    /// Replaces the current project with one read from a file, repairing it first. The repairs
+    /// are kept in `load_warnings`.
+    pub fn load_project(&mut self, mut project: Project) {
+        self.load_warnings = project.timeline.validate_and_repair();
+        // Only the setters check the rate; a file can carry anything
+        if project.fps == 0 || project.fps_den == 0 {
+            self.load_warnings.push(format!("Project had an invalid frame rate {}/{}; reset to {}/{}", project.fps, project.fps_den, default_fps(), default_fps_den()));
+            project.fps = default_fps();
+            project.fps_den = default_fps_den();
+        }
+        // Continue numbering after the ids this project was saved with
+        let timeline = &project.timeline;
+        let ids = timeline.all_clips().map(|c| c.id.as_str()).chain(timeline.tracks.iter().map(|t| t.id.as_str()));
+        self.next_id = ids.filter_map(counter_after).max().unwrap_or(1);
+        self.project = Some(project);
+        self.current_file_path = None; // Path is unknown until Swift sets it.
+        self.set_dirty(false); // A freshly loaded project is not dirty.
+        self.revision += 1;
+        self.clear_history();
+        self.timeline_changed();
+        self.emit(&EngineEvent::TimelineChanged(self.get_timeline()));
+    }
+
+    /// Restores the timeline snapshot taken before the last edit. Refused while a transaction
+    /// is open.
+    pub fn undo(&mut self) -> bool {
+        if self.transaction.is_some() {
+            return false; // It would pop edits that belong to the open transaction
+        }
+        if let Some(ref mut project) = self.project {
+            if let Some(previous) = self.undo_stack.pop() {
+                let current = std::mem::replace(&mut project.timeline, previous);
+                self.redo_stack.push(current);
+                project.update_modified_time();
+                let event = EngineEvent::TimelineChanged(project.timeline.clone());
+                self.set_dirty(true);
+                self.revision += 1;
+                self.timeline_changed();
+                self.emit(&event);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Re-applies the last undone edit. Refused while a transaction is open.
+    pub fn redo(&mut self) -> bool {
+        if self.transaction.is_some() {
+            return false;
+        }
+        if let Some(ref mut project) = self.project {
+            if let Some(next) = self.redo_stack.pop() {
+                let current = std::mem::replace(&mut project.timeline, next);
+                self.undo_stack.push(current);
+                project.update_modified_time();
+                let event = EngineEvent::TimelineChanged(project.timeline.clone());
+                self.set_dirty(true);
+                self.revision += 1;
+                self.timeline_changed();
+                self.emit(&event);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Drops all undo/redo history, e.g. when a different project is loaded. An open
+    /// transaction is abandoned with it.
+    pub fn clear_history(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.transaction = None;
+    }
+
+    /// Starts grouping commands into one undo step. Events are held back until the commit.
+    /// Returns false if there is no project or a transaction is already open.
+    pub fn begin_transaction(&mut self) -> bool {
+        match self.project.as_ref() {
+            Some(project) if self.transaction.is_none() => {
+                self.transaction = Some(Transaction {
+                    project_before: project.clone(),
+                    undo_depth: self.undo_stack.len(),
+                    redo_stack: self.redo_stack.clone(),
+                    was_dirty: self.is_dirty,
+                    revision: self.revision,
+                });
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Folds every edit since `begin_transaction` into a single undo step and emits one
+    /// `TimelineChanged`. Returns false if no transaction is open.
+    pub fn commit_transaction(&mut self) -> bool {
+        let Some(transaction) = self.transaction.take() else { return false };
+        self.undo_stack.truncate(transaction.undo_depth);
+        if let Some(project) = self.project.as_ref() {
+            if project.timeline != transaction.project_before.timeline {
+                self.undo_stack.push(transaction.project_before.timeline);
+            }
+            self.emit(&EngineEvent::TimelineChanged(project.timeline.clone()));
+        }
+        true
+    }
+
+    /// Throws away every edit since `begin_transaction`, restoring the project, undo history,
+    /// dirty flag and revision as they were. Returns false if no transaction is open.
+    pub fn rollback_transaction(&mut self) -> bool {
+        let Some(transaction) = self.transaction.take() else { return false };
+        self.undo_stack.truncate(transaction.undo_depth);
+        self.redo_stack = transaction.redo_stack;
+        let settings_changed = self.project.as_ref().is_some_and(|p| p.settings() != transaction.project_before.settings());
+        self.project = Some(transaction.project_before);
+        self.set_dirty(transaction.was_dirty);
+        self.revision = transaction.revision;
+        self.timeline_changed();
+        self.emit(&EngineEvent::TimelineChanged(self.get_timeline()));
+        if settings_changed {
+            self.emit(&EngineEvent::ProjectChanged(self.project.as_ref().unwrap().settings()));
+        }
+        true
+    }
+
+    pub fn get_clip_for_time(&self) -> Option<(Clip, u64)> { // (Clip, time_within_clip)
+        self.clip_at_time(self.playback_state.time_ms)
+    }
+
+    /// The clip showing at timeline time `time` and the source time inside it, wherever the
+    /// playhead is. None in a gap or past the end.
+    pub fn clip_at_time(&self, time: u64) -> Option<(Clip, u64)> {
+        if let Some(ref project) = self.project {
+            // Solo plays the one clip from time 0, as if it were the whole timeline
+            if let Some(clip) = self.solo_clip_id.as_ref().and_then(|id| project.timeline.clip_by_id(id)) {
+                return (time < clip.timeline_duration()).then(|| (clip.clone(), clip.source_time_at(time)));
+            }
+            // Walk tracks from the top of the stack down; the first visible clip wins.
+            for (track_idx, track) in project.timeline.tracks.iter().enumerate().rev() {
+                if track.kind == TrackKind::Audio {
+                    continue;
+                }
+                let found = match self.cached_offsets(track_idx) {
+                    Some(offsets) => track.clip_at_with(offsets, time),
+                    None => track.clip_at(time),
+                };
+                if let Some((clip, offset)) = found {
+                    if clip.is_gap() {
+                        continue; // Nothing to show during a gap
+                    }
+                    return Some((clip.clone(), clip.source_time_at(offset)));
+                }
+            }
+        }
+        None
+    }
+
+    /// Index of the clip occupying timeline time `time_ms` on a track. Gaps count as empty.
+    pub fn clip_index_at(&self, track: usize, time_ms: u64) -> Option<usize> {
+        let offsets = self.track_offsets(track);
+        let idx = offsets.partition_point(|&b| b <= time_ms).checked_sub(1)?;
+        let clip = self.project.as_ref()?.timeline.clips(track).get(idx)?;
+        (!clip.is_gap()).then_some(idx)
+    }
+
+    /// The transition on the main track under the playhead, if two clips are mixing right now.
+    pub fn get_transition_for_time(&self) -> Option<ActiveTransition> {
+        self.project.as_ref()?.timeline.transition_at(MAIN_TRACK, self.playback_state.time_ms)
+    }
+
+    /// The audio clip playing at the current time, with the source time inside it.
+    pub fn get_audio_clip_for_time(&self) -> Option<(Clip, u64)> {
+        let time = self.playback_state.time_ms;
+        self.project.as_ref()?.timeline.audio_clips.iter()
+            .find(|a| time >= a.start_ms && time < a.end_ms())
+            .map(|a| (a.clip.clone(), a.clip.source_time_at(time - a.start_ms)))
+    }
+}
+
+/// Derives a new id from `base` using the engine's counter, e.g. `clip#7`. A counter already on
+/// `base` is replaced rather than appended to, so ids don't grow with repeated cuts.
+fn gen_id(next_id: &mut u64, base: &str) -> String {
+    let root = base.split_once('#').map_or(base, |(root, _)| root);
+    let id = format!("{}#{}", root, *next_id);
+    *next_id += 1;
+    id
+}
+
+// Counter of an id in the `gen_id` format, if any.
+fn id_counter(id: &str) -> Option<u64> {
+    id.rsplit_once('#').and_then(|(_, n)| n.parse().ok())
+}
+
+// The counter `gen_id` must start from to never repeat `id`. None for ids not in the `gen_id`
+// format, and for counters so close to u64::MAX that the counter couldn't move past them; no
+// generated id ever gets that high, so they can't collide anyway.
+fn counter_after(id: &str) -> Option<u64> {
+    id_counter(id)?.checked_add(1).filter(|&next| next < u64::MAX)
+}
+
+// Moves the counter past an id that didn't come from `gen_id`, so no generated id can repeat it.
+fn reserve_id(next_id: &mut u64, id: &str) {
+    if let Some(next) = counter_after(id) {
+        *next_id = (*next_id).max(next);
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// --------------------
+// FFI Boundary
+// --------------------
+
+#[no_mangle]
+pub extern "C" fn engine_new() -> *mut Engine {
+    Box::into_raw(Box::new(Engine::new()))
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_free(engine: *mut Engine) {
+    if !engine.is_null() {
+        unsafe { let _ = Box::from_raw(engine); }
+    }
+}
+
+/// Inserts a clip at `idx` on the main track. Returns 0 if it landed at `idx`, 1 if `idx` was past
+/// the end and the clip was appended instead, or -1 if a pointer is null or the clip has an
+/// empty url or `out_ms <= in_ms`.
+#[no_mangle]
+pub extern "C" fn engine_add_clip(engine: *mut Engine, id: *const c_char, url: *const c_char, in_ms: u64, out_ms: u64, idx: usize) -> i32 {
+    engine_add_clip_to_track(engine, MAIN_TRACK, id, url, in_ms, out_ms, idx)
+}
+
+/// Inserts a still image at `idx` on the main track, lasting the project's default still
+/// duration. Returns the same codes as `engine_add_clip`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_add_still(engine: *mut Engine, id: *const c_char, url: *const c_char, idx: usize) -> i32 {
+    if engine.is_null() { return -1; }
+    let duration_ms = unsafe { &*engine }.project.as_ref().map_or_else(default_still_duration_ms, |p| p.default_still_duration_ms);
+    engine_add_clip(engine, id, url, 0, duration_ms, idx)
+}
+
+/// `engine_add_clip` for callers that only need to know whether the clip was accepted.
+#[no_mangle]
+pub extern "C" fn engine_try_add_clip(engine: *mut Engine, id: *const c_char, url: *const c_char, in_ms: u64, out_ms: u64, idx: usize) -> bool {
+    engine_add_clip(engine, id, url, in_ms, out_ms, idx) >= 0
+}
+
+/// Updates the url and range of the main-track clip with this id in place, or appends a new clip
+/// if there is none. A new url also renames the clip after its file. Returns true for an update,
+/// false for an insert. An empty url or
+/// `out_ms <= in_ms` changes nothing and returns false.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_upsert_clip(engine: *mut Engine, id: *const c_char, url: *const c_char, in_ms: u64, out_ms: u64) -> bool {
+    if engine.is_null() || id.is_null() || url.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    let id = unsafe { CStr::from_ptr(id).to_string_lossy().into_owned() };
+    let url = unsafe { CStr::from_ptr(url).to_string_lossy().into_owned() };
+    let clip = Clip::new(id, url, in_ms, out_ms);
+    if !clip.is_valid() { return false; }
+    let exists = eng.project.as_ref().is_some_and(|p| p.timeline.clips(MAIN_TRACK).iter().any(|c| c.id == clip.id));
+    eng.handle(Command::UpsertClip(MAIN_TRACK, clip));
+    exists
+}
+
+/// Adds a clip to the end of the main track. Returns the index it was appended at, or -1 for a
+/// null pointer or a clip with an empty url or `out_ms <= in_ms`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_append_clip(engine: *mut Engine, id: *const c_char, url: *const c_char, in_ms: u64, out_ms: u64) -> i64 {
+    if engine.is_null() || id.is_null() || url.is_null() { return -1; }
+    let eng = unsafe { &mut *engine };
+    let id = unsafe { CStr::from_ptr(id).to_string_lossy().into_owned() };
+    let url = unsafe { CStr::from_ptr(url).to_string_lossy().into_owned() };
+    let clip = Clip::new(id, url, in_ms, out_ms);
+    if !clip.is_valid() { return -1; }
+    let idx = eng.project.as_ref().map_or(0, |p| p.timeline.clips(MAIN_TRACK).len());
+    eng.handle(Command::AddClip(MAIN_TRACK, clip, idx));
+    idx as i64
+}
+
+/// Same as `engine_add_clip` but targets any track. Returns -1 if the track doesn't exist or the
+/// clip is rejected.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_add_clip_to_track(engine: *mut Engine, track: usize, id: *const c_char, url: *const c_char, in_ms: u64, out_ms: u64, idx: usize) -> i32 {
+    if engine.is_null() || id.is_null() || url.is_null() { return -1; }
+    let eng = unsafe { &mut *engine };
+    let clip_count = match eng.project.as_ref().and_then(|p| p.timeline.tracks.get(track)) {
+        Some(t) => t.clips.len(),
+        None => return -1,
+    };
+    let id = unsafe { CStr::from_ptr(id).to_string_lossy().into_owned() };
+    let url = unsafe { CStr::from_ptr(url).to_string_lossy().into_owned() };
+    let clip = Clip::new(id, url, in_ms, out_ms);
+    if !clip.is_valid() { return -1; }
+    eng.handle(Command::AddClip(track, clip, idx));
+    if idx > clip_count { 1 } else { 0 }
+}
+
+// One file found by the media scanner
+#[derive(Deserialize)]
+struct ManifestEntry {
+    url: String,
+    #[serde(default)]
+    duration_ms: Option<u64>,
+}
+
+/// Inserts every file from a scanner manifest (`[{"url": ..., "duration_ms": ...}]`) at
+/// `start_idx` as full-length clips, in one undoable edit. Entries without a url or a duration are
+/// skipped and counted in `out_skipped` if it's non-null. Returns the number added, or -1 for bad JSON.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_import_clips_from_manifest(engine: *mut Engine, json_data: *const c_char, start_idx: usize, out_skipped: *mut usize) -> i64 {
+    if engine.is_null() || json_data.is_null() { return -1; }
+    let eng = unsafe { &mut *engine };
+    let json = unsafe { CStr::from_ptr(json_data).to_string_lossy() };
+
+    let entries = match serde_json::from_str::<Vec<ManifestEntry>>(&json) {
+        Ok(entries) => entries,
+        Err(e) => {
+            println!("engine_import_clips_from_manifest - Deserialization error: {}", e);
+            return -1;
+        }
+    };
+    let mut skipped = 0;
+    let mut clips = Vec::new();
+    for entry in entries {
+        match entry.duration_ms.filter(|&d| d > 0 && !entry.url.is_empty()) {
+            Some(duration) => {
+                let mut clip = Clip::new(eng.gen_id("import"), entry.url, 0, duration);
+                clip.source_duration_ms = Some(duration);
+                clips.push(clip);
+            }
+            None => skipped += 1,
+        }
+    }
+    if !out_skipped.is_null() {
+        unsafe { *out_skipped = skipped; }
+    }
+    let added = clips.len();
+    if added > 0 {
+        eng.handle(Command::InsertClips(MAIN_TRACK, clips, start_idx));
+    }
+    added as i64
+}
+
+/// Inserts a new clip at timeline position `time_ms` on the main track, splitting the clip there
+/// if the time falls inside it. Times past the end append. An empty url or `out_ms <= in_ms`
+/// changes nothing.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_insert_clip_at_time(engine: *mut Engine, id: *const c_char, url: *const c_char, in_ms: u64, out_ms: u64, time_ms: u64) {
+    if engine.is_null() || id.is_null() || url.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    let id = unsafe { CStr::from_ptr(id).to_string_lossy().into_owned() };
+    let url = unsafe { CStr::from_ptr(url).to_string_lossy().into_owned() };
+    eng.handle(Command::InsertClipAtTime(MAIN_TRACK, Clip::new(id, url, in_ms, out_ms), time_ms));
+}
+
+/// Places a new clip at `time_ms` on the main track, replacing the content it covers. Clips
+/// partly under it are trimmed and nothing downstream moves. An empty url or `out_ms <= in_ms`
+/// changes nothing.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_overwrite_clip_at_time(engine: *mut Engine, id: *const c_char, url: *const c_char, in_ms: u64, out_ms: u64, time_ms: u64) {
+    if engine.is_null() || id.is_null() || url.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    let id = unsafe { CStr::from_ptr(id).to_string_lossy().into_owned() };
+    let url = unsafe { CStr::from_ptr(url).to_string_lossy().into_owned() };
+    eng.handle(Command::OverwriteClipAtTime(MAIN_TRACK, Clip::new(id, url, in_ms, out_ms), time_ms));
+}
+
+/// Opens `duration_ms` of empty space at `time_ms` on the main track, splitting the clip there if
+/// needed. Everything after moves later; the space is a gap, so `engine_close_gaps` removes it.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_ripple_space(engine: *mut Engine, time_ms: u64, duration_ms: u64) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::RippleSpace(MAIN_TRACK, time_ms, duration_ms));
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_remove_clip(engine: *mut Engine, idx: usize) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::RemoveClip(MAIN_TRACK, idx));
+}
+
+/// Splits the clip at `idx` at source time `position`. Returns false if the cut was rejected,
+/// e.g. because one half would be shorter than the minimum clip duration.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_cut_clip(engine: *mut Engine, idx: usize, position: u64) -> bool {
+    if engine.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    let count_before = eng.project.as_ref().map_or(0, |p| p.timeline.clips(MAIN_TRACK).len());
+    eng.handle(Command::CutClip(MAIN_TRACK, idx, position));
+    eng.project.as_ref().map_or(0, |p| p.timeline.clips(MAIN_TRACK).len()) > count_before
+}
+
+/// Sets the shortest clip a cut may leave behind. Pass 0 to use one frame at the project frame rate.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_min_clip_duration(engine: *mut Engine, duration_ms: u64) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.min_clip_duration_ms = if duration_ms == 0 { None } else { Some(duration_ms) };
+}
+
+/// Moves the in point of the clip at `idx`; later clips shift to follow. Returns false if
+/// `new_in` would invert the clip.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_ripple_trim_start(engine: *mut Engine, idx: usize, new_in: u64) -> bool {
+    if engine.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    !matches!(eng.handle(Command::RippleTrimStart(MAIN_TRACK, idx, new_in)), EngineEvent::Unchanged)
+}
+
+/// Moves the out point of the clip at `idx`; later clips shift to follow. Returns false if
+/// `new_out` would invert the clip.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_ripple_trim_end(engine: *mut Engine, idx: usize, new_out: u64) -> bool {
+    if engine.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    !matches!(eng.handle(Command::RippleTrimEnd(MAIN_TRACK, idx, new_out)), EngineEvent::Unchanged)
+}
+
+/// When enabled, edits move the playhead so it stays on the same frame of the main-track clip
+/// it was over, e.g. removing an earlier clip pulls it back. If that clip is removed the
+/// playhead stays where it is.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_keep_playhead_on_content(engine: *mut Engine, enabled: bool) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.keep_playhead_on_content = enabled;
+}
+
+/// Ripple mode is on by default. Turning it off makes removes and shortening trims leave a gap
+/// behind, so later clips keep their positions. A trimmed head leaves its gap before the clip and
+/// a trimmed tail after it. Lengthening trims still push later clips out.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_ripple_mode(engine: *mut Engine, enabled: bool) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.ripple_mode = enabled;
+}
+
+/// When enabled, cut positions are rounded to the nearest frame boundary at the project frame rate.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_snap_to_frames(engine: *mut Engine, enabled: bool) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.snap_to_frames = enabled;
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_update_clip_range(engine: *mut Engine, idx: usize, in_point: u64, out_point: u64) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::UpdateClipRange(MAIN_TRACK, idx, in_point, out_point));
+}
+
+/// Slip edit: shows a different part of the source by moving the clip's in and out points
+/// together by `delta_ms`, stopping at the ends of the media. The clip's place and length on the
+/// timeline don't change.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_slip_clip(engine: *mut Engine, idx: usize, delta_ms: i64) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::SlipClip(MAIN_TRACK, idx, delta_ms));
+}
+
+/// Slide edit: moves the clip at `idx` `delta_ms` later (or earlier, if negative) by lengthening
+/// one neighbour and shortening the other, so the total duration stays the same. Does nothing
+/// for the first or last clip, or if a neighbour would end up empty or past its media.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_slide_clip(engine: *mut Engine, idx: usize, delta_ms: i64) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::SlideClip(MAIN_TRACK, idx, delta_ms));
+}
+
+/// Moves the in point of the clip at `idx` by a signed delta. Clamped so the clip keeps at
+/// least 1 ms, rather than rejected, so a held key trims smoothly up to the limit.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_nudge_clip_in(engine: *mut Engine, idx: usize, delta_ms: i64) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    let Some(clip) = eng.project.as_ref().and_then(|p| p.timeline.clips(MAIN_TRACK).get(idx)) else { return };
+    let in_point = clip.in_point.saturating_add_signed(delta_ms).min(clip.out_point.saturating_sub(1));
+    let out_point = clip.out_point;
+    eng.handle(Command::UpdateClipRange(MAIN_TRACK, idx, in_point, out_point));
+}
+
+/// Moves the out point of the clip at `idx` by a signed delta. Clamped to keep at least 1 ms
+/// and to stay within the source media when its length is known.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_nudge_clip_out(engine: *mut Engine, idx: usize, delta_ms: i64) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    let Some(clip) = eng.project.as_ref().and_then(|p| p.timeline.clips(MAIN_TRACK).get(idx)) else { return };
+    let out_point = clip.clamp_to_source(clip.out_point.saturating_add_signed(delta_ms)).max(clip.in_point + 1);
+    let in_point = clip.in_point;
+    eng.handle(Command::UpdateClipRange(MAIN_TRACK, idx, in_point, out_point));
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_move_clip(engine: *mut Engine, from: usize, to: usize) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::MoveClip(MAIN_TRACK, from, to));
+}
+
+/// Copies the clip at `idx` to `idx + 1`. Returns the copy's index, or -1 if there is no such clip.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_duplicate_clip(engine: *mut Engine, idx: usize) -> i64 {
+    if engine.is_null() { return -1; }
+    let eng = unsafe { &mut *engine };
+    match eng.handle(Command::DuplicateClip(MAIN_TRACK, idx)) {
+        EngineEvent::ClipInserted(_, new_idx, _) => new_idx as i64,
+        _ => -1,
+    }
+}
+
+/// Copies the clip at `idx` to the clipboard. Returns false for an invalid index.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_copy_clip(engine: *mut Engine, idx: usize) -> bool {
+    if engine.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    eng.copy_clip(MAIN_TRACK, idx)
+}
+
+/// Pastes the clipboard clip at `idx` (appended if past the end). Returns false if the clipboard
+/// is empty or holds a gap.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_paste_clip(engine: *mut Engine, idx: usize) -> bool {
+    if engine.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    eng.paste_clip(MAIN_TRACK, idx)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_has_clipboard_content(engine: *const Engine) -> bool {
+    if engine.is_null() { return false; }
+    let eng = unsafe { &*engine };
+    eng.clipboard.is_some()
+}
+
+/// Merges the clip at `idx` with the next one. Returns false if they aren't contiguous segments of the same media.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_join_clips(engine: *mut Engine, idx: usize) -> bool {
+    if engine.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    let count_before = eng.project.as_ref().map_or(0, |p| p.timeline.clips(MAIN_TRACK).len());
+    eng.handle(Command::JoinClips(MAIN_TRACK, idx));
+    eng.project.as_ref().map_or(0, |p| p.timeline.clips(MAIN_TRACK).len()) < count_before
+}
+
+/// Whether `engine_join_clips(idx)` would join anything: the clips at `idx` and `idx + 1` are
+/// contiguous segments of the same media.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_can_join(engine: *const Engine, idx: usize) -> bool {
+    if engine.is_null() { return false; }
+    let eng = unsafe { &*engine };
+    let clips = eng.project.as_ref().map_or(&[][..], |p| p.timeline.clips(MAIN_TRACK));
+    match (clips.get(idx), idx.checked_add(1).and_then(|next| clips.get(next))) {
+        (Some(first), Some(second)) => first.joins_with(second),
+        _ => false,
+    }
+}
+
+/// Joins every run of contiguous segments of the same media on the main track, as repeated
+/// `engine_join_clips` would. Returns the number of joins made.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_consolidate(engine: *mut Engine) -> usize {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &mut *engine };
+    let count_before = eng.project.as_ref().map_or(0, |p| p.timeline.clips(MAIN_TRACK).len());
+    eng.handle(Command::Consolidate(MAIN_TRACK));
+    count_before - eng.project.as_ref().map_or(0, |p| p.timeline.clips(MAIN_TRACK).len())
+}
+
+/// Replaces the clip at `idx` with a gap of the same duration, leaving downstream clips in place.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_lift_clip(engine: *mut Engine, idx: usize) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::LiftClip(MAIN_TRACK, idx));
+}
+
+/// Number of gaps on the main track, e.g. left behind by lifts.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_gap_count(engine: *const Engine) -> usize {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().map_or(0, |p| p.timeline.clips(MAIN_TRACK).iter().filter(|c| c.is_gap()).count())
+}
+
+/// Removes every gap from the main track so the remaining clips play back to back.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_close_gaps(engine: *mut Engine) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::CloseGaps(MAIN_TRACK));
+}
+
+/// Reverses the order of the main-track clips. Ids, ranges and the total duration are kept.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_reverse_timeline(engine: *mut Engine) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::ReverseTimeline(MAIN_TRACK));
+}
+
+// Selection FFI functions
+
+/// Makes the clip at `idx` the only selected clip.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_select_clip(engine: *mut Engine, idx: usize) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    if let Some(clip) = eng.project.as_ref().and_then(|p| p.timeline.clips(MAIN_TRACK).get(idx)) {
+        eng.selected_clip_ids = vec![clip.id.clone()];
+    }
+}
+
+/// Adds the clip at `idx` to the selection, or removes it if it's already selected.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_toggle_select_clip(engine: *mut Engine, idx: usize) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    if let Some(id) = eng.project.as_ref().and_then(|p| p.timeline.clips(MAIN_TRACK).get(idx)).map(|c| c.id.clone()) {
+        match eng.selected_clip_ids.iter().position(|s| *s == id) {
+            Some(pos) => { eng.selected_clip_ids.remove(pos); }
+            None => eng.selected_clip_ids.push(id),
+        }
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_clear_selection(engine: *mut Engine) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.selected_clip_ids.clear();
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_selected_count(engine: *const Engine) -> usize {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &*engine };
+    eng.selected_clip_ids.len()
+}
+
+/// Id of the `idx`th selected clip, or null for an invalid index. Free with `free_rust_string`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_selected_clip_id(engine: *const Engine, idx: usize) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+    match eng.selected_clip_ids.get(idx) {
+        Some(id) => CString::new(id.clone()).unwrap().into_raw(),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Points the clip at `idx` at different media, e.g. full-res files in place of proxies. Timing
+/// and every other field are kept. Returns false for an empty url or invalid index.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_relink_clip(engine: *mut Engine, idx: usize, new_url: *const c_char) -> bool {
+    if engine.is_null() || new_url.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    let url = unsafe { CStr::from_ptr(new_url).to_string_lossy().into_owned() };
+    let clip_exists = eng.project.as_ref().is_some_and(|p| idx < p.timeline.clips(MAIN_TRACK).len());
+    if !clip_exists || url.is_empty() { return false; }
+    eng.handle(Command::RelinkClip(MAIN_TRACK, idx, url));
+    true
+}
+
+/// Records whether the clip's media is missing. Swift sets this after probing the file.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_clip_offline(engine: *mut Engine, idx: usize, offline: bool) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::SetClipOffline(MAIN_TRACK, idx, offline));
+}
+
+/// Disables or re-enables the clip at `idx`. A disabled clip keeps its place and duration, and
+/// playback info reports it so the compositor can show black.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_clip_enabled(engine: *mut Engine, idx: usize, enabled: bool) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::SetClipEnabled(MAIN_TRACK, idx, enabled));
+}
+
+/// Turns the clip at `idx` into a freeze frame holding source time `frame_time_ms` for its
+/// current duration, or back into normal playback. `frame_time_ms` is ignored when unfreezing.
+/// The in and out points are kept, so unfreezing plays the clip's original range again.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_clip_freeze(engine: *mut Engine, idx: usize, freeze: bool, frame_time_ms: u64) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::SetClipFreeze(MAIN_TRACK, idx, freeze, frame_time_ms));
+}
+
+/// Number of main-track clips whose media is missing.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_offline_clip_count(engine: *const Engine) -> usize {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().map_or(0, |p| p.timeline.clips(MAIN_TRACK).iter().filter(|c| c.is_offline).count())
+}
+
+/// Main-track index of the `n`th offline clip, or -1 if there are fewer than `n + 1`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_offline_clip_index(engine: *const Engine, n: usize) -> i64 {
+    if engine.is_null() { return -1; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref()
+        .and_then(|p| p.timeline.clips(MAIN_TRACK).iter().enumerate().filter(|(_, c)| c.is_offline).nth(n))
+        .map_or(-1, |(idx, _)| idx as i64)
+}
+
+/// Rewrites the start of every clip url matching `old_prefix`, e.g. after the media folder moved.
+/// Covers every track and the audio lane in one edit. Returns the number of clips changed.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_relink_by_prefix(engine: *mut Engine, old_prefix: *const c_char, new_prefix: *const c_char) -> usize {
+    if engine.is_null() || old_prefix.is_null() || new_prefix.is_null() { return 0; }
+    let eng = unsafe { &mut *engine };
+    let old_prefix = unsafe { CStr::from_ptr(old_prefix).to_string_lossy().into_owned() };
+    let new_prefix = unsafe { CStr::from_ptr(new_prefix).to_string_lossy().into_owned() };
+    if old_prefix.is_empty() { return 0; }
+    let matching = eng.project.as_ref().map_or(0, |p| p.timeline.all_clips().filter(|c| c.url.starts_with(&old_prefix)).count());
+    if matching > 0 {
+        eng.handle(Command::RelinkByPrefix(old_prefix, new_prefix));
+    }
+    matching
+}
+
+/// Swaps the in and out points of every inverted clip. Returns how many were fixed.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_normalize_ranges(engine: *mut Engine) -> usize {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &mut *engine };
+    let inverted = eng.project.as_ref().map_or(0, |p| p.timeline.all_clips().filter(|c| c.out_point < c.in_point).count());
+    if inverted > 0 {
+        eng.handle(Command::NormalizeRanges);
+    }
+    inverted
+}
+
+/// Razors every track at timeline time `time_ms`. Tracks with a boundary there, or no clip
+/// under it, are left alone.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_split_at_time(engine: *mut Engine, time_ms: u64) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::SplitAtTime(time_ms));
+}
+
+/// Index of the main-track clip at timeline time `time_ms`, or -1 in a gap or past the end.
+/// A time on a boundary belongs to the clip starting there.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_clip_index_at_time(engine: *const Engine, time_ms: u64) -> i64 {
+    if engine.is_null() { return -1; }
+    let eng = unsafe { &*engine };
+    eng.clip_index_at(MAIN_TRACK, time_ms).map_or(-1, |idx| idx as i64)
+}
+
+/// Current index of the main-track clip with this id, or -1 if there is none.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_clip_index_by_id(engine: *const Engine, id: *const c_char) -> i64 {
+    if engine.is_null() || id.is_null() { return -1; }
+    let eng = unsafe { &*engine };
+    let id = unsafe { CStr::from_ptr(id).to_string_lossy() };
+    eng.project.as_ref()
+        .and_then(|p| p.timeline.clips(MAIN_TRACK).iter().position(|c| c.id == id))
+        .map_or(-1, |idx| idx as i64)
+}
+
+// Id of the main-track clip `step` places after (or, if negative, before) the clip with this id.
+fn neighbour_clip_id(engine: *const Engine, id: *const c_char, step: isize) -> *mut c_char {
+    if engine.is_null() || id.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+    let id = unsafe { CStr::from_ptr(id).to_string_lossy() };
+    let Some(clips) = eng.project.as_ref().map(|p| p.timeline.clips(MAIN_TRACK)) else { return std::ptr::null_mut() };
+    let neighbour = clips.iter().position(|c| c.id == id)
+        .and_then(|idx| idx.checked_add_signed(step))
+        .and_then(|idx| clips.get(idx));
+    match neighbour {
+        Some(clip) => CString::new(clip.id.clone()).unwrap().into_raw(),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Id of the main-track clip after the one with this id, or null at the end or for an
+/// unknown id. Free with `free_rust_string`.
+#[no_mangle]
+pub extern "C" fn engine_get_next_clip_id(engine: *const Engine, id: *const c_char) -> *mut c_char {
+    neighbour_clip_id(engine, id, 1)
+}
+
+/// Id of the main-track clip before the one with this id, or null at the start or for an
+/// unknown id. Free with `free_rust_string`.
+#[no_mangle]
+pub extern "C" fn engine_get_prev_clip_id(engine: *const Engine, id: *const c_char) -> *mut c_char {
+    neighbour_clip_id(engine, id, -1)
+}
+
+/// Empties the timeline but keeps the project's name, settings and file path, unlike
+/// `engine_new_project`. Playback stops at the start.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_clear_timeline(engine: *mut Engine) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::ClearTimeline);
+}
+
+/// Groups every selected clip so group moves and removes treat them as one. Returns the new
+/// group id, or null if fewer than two clips are selected. Free with `free_rust_string`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_group_selected(engine: *mut Engine) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &mut *engine };
+    if eng.selected_clip_ids.len() < 2 { return std::ptr::null_mut(); }
+    eng.handle(Command::GroupSelected);
+    let first_selected = eng.selected_clip_ids.first().cloned().unwrap_or_default();
+    let group_id = eng.project.as_ref().and_then(|p| p.timeline.clip_by_id(&first_selected)).and_then(|c| c.group_id.clone());
+    match group_id {
+        Some(group_id) => CString::new(group_id).unwrap().into_raw(),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Dissolves a group; its clips stay where they are.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_ungroup(engine: *mut Engine, group_id: *const c_char) {
+    if engine.is_null() || group_id.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    let group_id = unsafe { CStr::from_ptr(group_id).to_string_lossy().into_owned() };
+    eng.handle(Command::Ungroup(group_id));
+}
+
+/// Group id of the clip at `idx`, or null if it isn't grouped. Free with `free_rust_string`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_clip_group_id(engine: *const Engine, idx: usize) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+    match eng.project.as_ref().and_then(|p| p.timeline.clips(MAIN_TRACK).get(idx)).and_then(|c| c.group_id.clone()) {
+        Some(group_id) => CString::new(group_id).unwrap().into_raw(),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Moves the clip at `idx` together with the rest of its main-track group, keeping their
+/// order. The block lands at index `to` among the clips outside the group. An ungrouped clip
+/// moves on its own.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_move_group(engine: *mut Engine, idx: usize, to: usize) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::MoveGroup(MAIN_TRACK, idx, to));
+}
+
+/// Deletes the clip at `idx` and every clip in its group, on any track, as one undoable edit.
+/// Returns the number removed.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_remove_group(engine: *mut Engine, idx: usize) -> usize {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &mut *engine };
+    let count = |eng: &Engine| eng.project.as_ref().map_or(0, |p| p.timeline.all_clips().count());
+    let count_before = count(eng);
+    eng.handle(Command::RemoveGroup(MAIN_TRACK, idx));
+    count_before - count(eng)
+}
+
+/// Deletes every selected clip as one undoable edit. Returns the number removed.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_remove_selected(engine: *mut Engine) -> usize {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &mut *engine };
+    let count = |eng: &Engine| eng.project.as_ref().map_or(0, |p| {
+        p.timeline.tracks.iter().map(|t| t.clips.len()).sum::<usize>() + p.timeline.audio_clips.len()
+    });
+    let count_before = count(eng);
+    eng.handle(Command::RemoveSelected);
+    count_before - count(eng)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_clip_count(engine: *const Engine) -> usize {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().map_or(0, |p| p.timeline.clips(MAIN_TRACK).len())
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_clip_id(engine: *const Engine, idx: usize) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+    if let Some(clip) = eng.project.as_ref().and_then(|p| p.timeline.clips(MAIN_TRACK).get(idx)) {
+        CString::new(clip.id.clone()).unwrap().into_raw()
+    } else {
+        std::ptr::null_mut()
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_clip_url(engine: *const Engine, idx: usize) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+    if let Some(clip) = eng.project.as_ref().and_then(|p| p.timeline.clips(MAIN_TRACK).get(idx)) {
+        CString::new(clip.url.clone()).unwrap().into_raw()
+    } else {
+        std::ptr::null_mut()
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_clip_name(engine: *const Engine, idx: usize) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+    if let Some(clip) = eng.project.as_ref().and_then(|p| p.timeline.clips(MAIN_TRACK).get(idx)) {
+        CString::new(clip.name.clone()).unwrap().into_raw()
+    } else {
+        std::ptr::null_mut()
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_clip_name(engine: *mut Engine, idx: usize, name: *const c_char) {
+    if engine.is_null() || name.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    let name = unsafe { CStr::from_ptr(name).to_string_lossy().into_owned() };
+    eng.handle(Command::SetClipName(MAIN_TRACK, idx, name));
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_clip_in_point(engine: *const Engine, idx: usize) -> u64 {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().and_then(|p| p.timeline.clips(MAIN_TRACK).get(idx)).map_or(0, |c| c.in_point)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_clip_out_point(engine: *const Engine, idx: usize) -> u64 {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().and_then(|p| p.timeline.clips(MAIN_TRACK).get(idx)).map_or(0, |c| c.out_point)
+}
+
+/// Sets the linear gain of a clip, clamped to 0.0..=4.0.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_clip_volume(engine: *mut Engine, idx: usize, volume: f32) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::SetClipVolume(MAIN_TRACK, idx, volume));
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_clip_volume(engine: *const Engine, idx: usize) -> f32 {
+    if engine.is_null() { return 0.0; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().and_then(|p| p.timeline.clips(MAIN_TRACK).get(idx)).map_or(0.0, |c| c.volume)
+}
+
+/// Sets a clip's playback rate. Values <= 0 are ignored.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_clip_speed(engine: *mut Engine, idx: usize, speed: f32) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::SetClipSpeed(MAIN_TRACK, idx, speed));
+}
+
+/// Retimes the clip at `idx` so it lasts `target_ms` on the timeline, by setting its speed to
+/// source duration / `target_ms`. The in and out points stay. Returns false for a target of 0,
+/// an invalid index or an empty clip.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_fit_clip_to_duration(engine: *mut Engine, idx: usize, target_ms: u64) -> bool {
+    if engine.is_null() || target_ms == 0 { return false; }
+    let eng = unsafe { &mut *engine };
+    let Some(clip) = eng.project.as_ref().and_then(|p| p.timeline.clips(MAIN_TRACK).get(idx)) else { return false };
+    let speed = (clip.duration() as f64 / target_ms as f64) as f32;
+    if !(speed.is_finite() && speed > 0.0) { return false; }
+    eng.handle(Command::SetClipSpeed(MAIN_TRACK, idx, speed));
+    true
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_clip_speed(engine: *const Engine, idx: usize) -> f32 {
+    if engine.is_null() { return 0.0; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().and_then(|p| p.timeline.clips(MAIN_TRACK).get(idx)).map_or(0.0, |c| c.speed)
+}
+
+/// Records the probed length of the clip's media so in/out edits can't run past it. Pass 0 if unknown.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_clip_source_duration(engine: *mut Engine, idx: usize, duration_ms: u64) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    let duration = if duration_ms == 0 { None } else { Some(duration_ms) };
+    eng.handle(Command::SetClipSourceDuration(MAIN_TRACK, idx, duration));
+}
+
+/// Sets the clip's position, scale and rotation. Returns false for an invalid index or a
+/// non-positive scale.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_clip_transform(engine: *mut Engine, idx: usize, x: f32, y: f32, scale: f32, rotation_deg: f32) -> bool {
+    if engine.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    let transform = Transform { x, y, scale, rotation_deg };
+    let clip_exists = eng.project.as_ref().is_some_and(|p| idx < p.timeline.clips(MAIN_TRACK).len());
+    if !clip_exists || !transform.is_valid() { return false; }
+    eng.handle(Command::SetClipTransform(MAIN_TRACK, idx, transform));
+    true
+}
+
+/// The clip's transform, or identity for an invalid index.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_clip_transform(engine: *const Engine, idx: usize) -> Transform {
+    if engine.is_null() { return Transform::default(); }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().and_then(|p| p.timeline.clips(MAIN_TRACK).get(idx)).map_or(Transform::default(), |c| c.transform)
+}
+
+/// Tags the clip at `idx` with a packed RGBA label color. Pass 0 to clear it.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_clip_label(engine: *mut Engine, idx: usize, color: u32) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::SetClipLabel(MAIN_TRACK, idx, color));
+}
+
+/// The clip's label color, or 0 for no label or an invalid index.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_clip_label(engine: *const Engine, idx: usize) -> u32 {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().and_then(|p| p.timeline.clips(MAIN_TRACK).get(idx)).map_or(0, |c| c.label_color)
+}
+
+/// Indices of the main-track clips tagged with `color`, as a JSON array. The caller frees the
+/// string with `free_rust_string`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_clips_with_label(engine: *const Engine, color: u32) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+    let indices: Vec<usize> = eng.project.as_ref().map_or_else(Vec::new, |p| {
+        p.timeline.clips(MAIN_TRACK).iter().enumerate().filter(|(_, c)| c.label_color == color).map(|(idx, _)| idx).collect()
+    });
+    CString::new(serde_json::json!(indices).to_string()).unwrap().into_raw()
+}
+
+/// Indices of the main-track clips whose url contains `substring`, ignoring case, as a JSON
+/// array. The caller frees the string with `free_rust_string`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_find_clips_by_url(engine: *const Engine, substring: *const c_char) -> *mut c_char {
+    if engine.is_null() || substring.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+    let needle = unsafe { CStr::from_ptr(substring).to_string_lossy().to_lowercase() };
+    let indices: Vec<usize> = eng.project.as_ref().map_or_else(Vec::new, |p| {
+        p.timeline.clips(MAIN_TRACK).iter().enumerate().filter(|(_, c)| c.url.to_lowercase().contains(&needle)).map(|(idx, _)| idx).collect()
+    });
+    CString::new(serde_json::json!(indices).to_string()).unwrap().into_raw()
+}
+
+/// Sets the source time used for the clip's thumbnail, clamped to its in and out points.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_clip_poster_time(engine: *mut Engine, idx: usize, time_ms: u64) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::SetClipPosterTime(MAIN_TRACK, idx, time_ms));
+}
+
+/// Source time of the clip's thumbnail frame, or 0 for an invalid index.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_clip_poster_time(engine: *const Engine, idx: usize) -> u64 {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().and_then(|p| p.timeline.clips(MAIN_TRACK).get(idx)).map_or(0, |c| c.poster_time())
+}
+
+/// Sets the clip's fade-in and fade-out lengths. Each is clamped to half the clip's duration.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_clip_fades(engine: *mut Engine, idx: usize, fade_in_ms: u64, fade_out_ms: u64) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::SetClipFades(MAIN_TRACK, idx, fade_in_ms, fade_out_ms));
+}
+
+/// Opacity the compositor should apply `offset_ms` into the clip's span on the timeline, i.e. the
+/// playhead time minus the clip's start, or 1.0 for an invalid index. This is not the source
+/// time in `PlaybackClipInfo.time_in_clip_ms`, which differs once the clip is trimmed or retimed.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_clip_opacity_at(engine: *const Engine, idx: usize, offset_ms: u64) -> f32 {
+    if engine.is_null() { return 1.0; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().and_then(|p| p.timeline.clips(MAIN_TRACK).get(idx)).map_or(1.0, |c| c.opacity_at(offset_ms))
+}
+
+/// On-timeline duration of the clip (its speed applied), or 0 for an invalid index.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_clip_duration(engine: *const Engine, idx: usize) -> u64 {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().and_then(|p| p.timeline.clips(MAIN_TRACK).get(idx)).map_or(0, |c| c.timeline_duration())
+}
+
+/// Total length of the timeline in ms, or 0 when there's no project.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_total_duration(engine: *const Engine) -> u64 {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().map_or(0, |p| p.timeline.total_duration())
+}
+
+/// Places an audio clip at an absolute timeline position. It plays alongside the video tracks.
+/// An empty url or `out_ms <= in_ms` changes nothing.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_add_audio_clip(engine: *mut Engine, id: *const c_char, url: *const c_char, in_ms: u64, out_ms: u64, start_ms: u64) {
+    if engine.is_null() || id.is_null() || url.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    let id = unsafe { CStr::from_ptr(id).to_string_lossy().into_owned() };
+    let url = unsafe { CStr::from_ptr(url).to_string_lossy().into_owned() };
+    let clip = Clip::new(id, url, in_ms, out_ms);
+    eng.handle(Command::AddAudioClip(clip, start_ms));
+}
+
+// Track FFI functions
+
+/// Appends an empty track on top of the stack. Returns its index, or -1 on failure.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_add_track(engine: *mut Engine, kind: TrackKind) -> i64 {
+    if engine.is_null() { return -1; }
+    let eng = unsafe { &mut *engine };
+    if eng.project.is_none() { return -1; }
+    eng.handle(Command::AddTrack(kind));
+    eng.project.as_ref().map_or(-1, |p| p.timeline.tracks.len() as i64 - 1)
+}
+
+/// Removes a track and its clips. The main track can't be removed.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_remove_track(engine: *mut Engine, track: usize) -> bool {
+    if engine.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    let count_before = eng.project.as_ref().map_or(0, |p| p.timeline.tracks.len());
+    eng.handle(Command::RemoveTrack(track));
+    eng.project.as_ref().map_or(0, |p| p.timeline.tracks.len()) < count_before
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_track_count(engine: *const Engine) -> usize {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().map_or(0, |p| p.timeline.tracks.len())
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_track_kind(engine: *const Engine, track: usize) -> TrackKind {
+    if engine.is_null() { return TrackKind::Video; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().and_then(|p| p.timeline.tracks.get(track)).map_or(TrackKind::Video, |t| t.kind)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_track_clip_count(engine: *const Engine, track: usize) -> usize {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().map_or(0, |p| p.timeline.clips(track).len())
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_remove_clip_from_track(engine: *mut Engine, track: usize, idx: usize) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::RemoveClip(track, idx));
+}
+
+/// Registers a callback invoked after every change to the timeline or playback state.
+/// Pass a null callback to stop receiving events.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_event_callback(engine: *mut Engine, callback: EngineEventCallback, user_data: *mut c_void) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.event_callback = callback;
+    eng.event_user_data = user_data;
+}
+
+/// Adds a transition between the clip at `idx` and the next one. Returns false if either clip is
+/// missing or shorter than `duration_ms`, or the next clip has less than `duration_ms` of media
+/// before its in point.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_add_transition(engine: *mut Engine, idx: usize, duration_ms: u64, kind: TransitionKind) -> bool {
+    if engine.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::AddTransition(MAIN_TRACK, idx, duration_ms, kind));
+
+    // Succeeds if the pair now carries exactly this transition, even if it already did
+    eng.project.as_ref().is_some_and(|p| {
+        let clips = p.timeline.clips(MAIN_TRACK);
+        match (clips.get(idx), clips.get(idx + 1)) {
+            (Some(from), Some(to)) => p.timeline.transitions.iter().any(|t| {
+                t.from_clip_id == from.id && t.to_clip_id == to.id && t.duration_ms == duration_ms && t.kind == kind
+            }),
+            _ => false,
+        }
+    })
+}
+
+/// Registers a callback fired when the unsaved-changes state flips, for the window's modified
+/// indicator. Pass a null callback to unregister.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_dirty_callback(engine: *mut Engine, callback: DirtyCallback, user_data: *mut c_void) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.dirty_callback = callback;
+    eng.dirty_user_data = user_data;
+}
+
+// Marker FFI functions
+
+/// Adds a marker. Returns false if a marker with the same id already exists.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_add_marker(engine: *mut Engine, id: *const c_char, time_ms: u64, label: *const c_char, color: u32) -> bool {
+    if engine.is_null() || id.is_null() || label.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    let id = unsafe { CStr::from_ptr(id).to_string_lossy().into_owned() };
+    let label = unsafe { CStr::from_ptr(label).to_string_lossy().into_owned() };
+    let marker = Marker { id, time_ms, label, color };
+    !matches!(eng.handle(Command::AddMarker(marker)), EngineEvent::Unchanged)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_remove_marker(engine: *mut Engine, id: *const c_char) -> bool {
+    if engine.is_null() || id.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    let id = unsafe { CStr::from_ptr(id).to_string_lossy().into_owned() };
+    !matches!(eng.handle(Command::RemoveMarker(id)), EngineEvent::Unchanged)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_move_marker(engine: *mut Engine, id: *const c_char, time_ms: u64) -> bool {
+    if engine.is_null() || id.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    let id = unsafe { CStr::from_ptr(id).to_string_lossy().into_owned() };
+    !matches!(eng.handle(Command::MoveMarker(id, time_ms)), EngineEvent::Unchanged)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_marker_count(engine: *const Engine) -> usize {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().map_or(0, |p| p.timeline.markers.len())
+}
+
+/// Markers are indexed in time order.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_marker_id(engine: *const Engine, idx: usize) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+    if let Some(marker) = eng.project.as_ref().and_then(|p| p.timeline.markers.get(idx)) {
+        CString::new(marker.id.clone()).unwrap().into_raw()
+    } else {
+        std::ptr::null_mut()
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_marker_label(engine: *const Engine, idx: usize) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+    if let Some(marker) = eng.project.as_ref().and_then(|p| p.timeline.markers.get(idx)) {
+        CString::new(marker.label.clone()).unwrap().into_raw()
+    } else {
+        std::ptr::null_mut()
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_marker_time(engine: *const Engine, idx: usize) -> u64 {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().and_then(|p| p.timeline.markers.get(idx)).map_or(0, |m| m.time_ms)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_marker_color(engine: *const Engine, idx: usize) -> u32 {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().and_then(|p| p.timeline.markers.get(idx)).map_or(0, |m| m.color)
+}
+
+/// Time of the first marker after `time_ms`, or -1 if there is none.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_next_marker_after(engine: *const Engine, time_ms: u64) -> i64 {
+    if engine.is_null() { return -1; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().and_then(|p| p.timeline.next_marker_after(time_ms)).map_or(-1, |m| m.time_ms as i64)
+}
+
+/// Previews the clip at `idx` on its own: playback time 0 maps to its in point and every other
+/// clip is ignored. Not saved. Returns false for an invalid index.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_solo_clip(engine: *mut Engine, idx: usize) -> bool {
+    if engine.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    match eng.project.as_ref().and_then(|p| p.timeline.clips(MAIN_TRACK).get(idx)) {
+        Some(clip) => {
+            eng.solo_clip_id = Some(clip.id.clone());
+            true
+        }
+        None => false,
+    }
+}
+
+/// Ends solo preview; playback shows the whole timeline again.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_clear_solo(engine: *mut Engine) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.solo_clip_id = None;
+}
+
+// Undo/redo FFI functions
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_undo(engine: *mut Engine) -> bool {
+    if engine.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    eng.undo()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_redo(engine: *mut Engine) -> bool {
+    if engine.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    eng.redo()
+}
+
+/// Groups the following commands into one undo step, e.g. split + move + trim. Events are held
+/// back until `engine_commit_transaction`. Returns false if one is already open.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_begin_transaction(engine: *mut Engine) -> bool {
+    if engine.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    eng.begin_transaction()
+}
+
+/// Ends the open transaction as a single undo step and emits one `TimelineChanged`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_commit_transaction(engine: *mut Engine) -> bool {
+    if engine.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    eng.commit_transaction()
+}
+
+/// Ends the open transaction by undoing everything it did.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_rollback_transaction(engine: *mut Engine) -> bool {
+    if engine.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    eng.rollback_transaction()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_can_undo(engine: *const Engine) -> bool {
+    if engine.is_null() { return false; }
+    let eng = unsafe { &*engine };
+    !eng.undo_stack.is_empty() && eng.transaction.is_none()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_can_redo(engine: *const Engine) -> bool {
+    if engine.is_null() { return false; }
+    let eng = unsafe { &*engine };
+    !eng.redo_stack.is_empty() && eng.transaction.is_none()
+}
+
+// Playback FFI functions
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_play(engine: *mut Engine) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::Play);
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_pause(engine: *mut Engine) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::Pause);
+}
+
+/// Pauses and rewinds to the start, unlike `engine_pause` which keeps the time.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_stop(engine: *mut Engine) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::Stop);
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_seek(engine: *mut Engine, time_ms: u64) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::Seek(time_ms));
+}
+
+/// Seeks to a typed `HH:MM:SS:FF` timecode in the project frame rate, clamped to the end of the
+/// timeline. Returns false if the timecode can't be parsed.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_seek_to_timecode(engine: *mut Engine, tc: *const c_char) -> bool {
+    if engine.is_null() || tc.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    let tc = unsafe { CStr::from_ptr(tc).to_string_lossy() };
+    let time = eng.project.as_ref().and_then(|p| p.timecode_to_frame(&tc).map(|frame| p.frame_to_ms(frame)));
+    match time {
+        Some(time) => {
+            eng.handle(Command::Seek(time));
+            true
+        }
+        None => false,
+    }
+}
+
+/// The playhead position as an `HH:MM:SS:FF` timecode. Free with `free_rust_string`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_current_timecode(engine: *const Engine) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+    match eng.project.as_ref() {
+        Some(p) => CString::new(p.frame_to_timecode(p.ms_to_frame(eng.playback_state.time_ms))).unwrap().into_raw(),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Seeks like `engine_seek` and returns where the playhead actually landed, which is clamped to
+/// the end of the timeline.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_seek_clamped(engine: *mut Engine, time_ms: u64) -> u64 {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::Seek(time_ms));
+    eng.playback_state.time_ms
+}
+
+/// Moves the playhead to the start of the timeline, e.g. for the Home key.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_seek_to_start(engine: *mut Engine) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::Seek(0));
+}
+
+/// Moves the playhead to the end of the timeline, e.g. for the End key.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_seek_to_end(engine: *mut Engine) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::Seek(u64::MAX)); // Seek clamps to the total duration
+}
+
+/// Pulls the playhead back to the end of the timeline if it is past it, pausing playback. Edits
+/// already do this; returns the playhead time.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_clamp_playhead(engine: *mut Engine) -> u64 {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &mut *engine };
+    eng.clamp_playhead();
+    eng.playback_state.time_ms
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_tick(engine: *mut Engine, delta_ms: u64) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::Tick(delta_ms));
+}
+
+/// Snap target for a drag: the nearest clip boundary or marker within `tolerance_ms` of
+/// `time_ms`, or `time_ms` unchanged if nothing is that close.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_snap_time(engine: *const Engine, time_ms: u64, tolerance_ms: u64) -> u64 {
+    if engine.is_null() { return time_ms; }
+    let eng = unsafe { &*engine };
+    eng.snap_time(time_ms, tolerance_ms)
+}
+
+/// Jumps to the next clip boundary. Returns the new playback time in ms.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_seek_to_next_edit(engine: *mut Engine) -> u64 {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &mut *engine };
+    eng.seek_to_next_edit()
+}
+
+/// Jumps to the previous clip boundary. Returns the new playback time in ms.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_seek_to_prev_edit(engine: *mut Engine) -> u64 {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &mut *engine };
+    eng.seek_to_prev_edit()
+}
+
+/// Enables looping between `start_ms` and `end_ms`. If the range is empty the whole timeline loops.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_loop(engine: *mut Engine, enabled: bool, start_ms: u64, end_ms: u64) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    let range = if start_ms < end_ms { Some((start_ms, end_ms)) } else { None };
+    eng.handle(Command::SetLoop(enabled, range));
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_clear_loop(engine: *mut Engine) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::SetLoop(false, None));
+}
+
+/// Sets the transport speed. Negative rates play backward; the magnitude is clamped to 0.1..=8.0
+/// and zero is ignored.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_playback_rate(engine: *mut Engine, rate: f32) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::SetRate(rate));
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_playback_rate(engine: *const Engine) -> f32 {
+    if engine.is_null() { return 0.0; }
+    let eng = unsafe { &*engine };
+    eng.playback_state.rate
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_tick_frames(engine: *mut Engine, frames: u64) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::TickFrames(frames));
+}
+
+/// Index of the frame under the playhead at the project frame rate.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_playback_frame(engine: *const Engine) -> u64 {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().map_or(0, |p| p.ms_to_frame(eng.playback_state.time_ms))
+}
+
+/// Sets the project frame rate to `fps / fps_den`, e.g. 30000/1001 for 29.97. Returns false if
+/// either is 0 or there's no project.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_fps(engine: *mut Engine, fps: u32, fps_den: u32) -> bool {
+    if engine.is_null() || fps == 0 || fps_den == 0 { return false; }
+    let eng = unsafe { &mut *engine };
+    if eng.project.is_none() { return false; }
+    eng.handle(Command::SetFrameRate(fps, fps_den));
+    true
+}
+
+/// Sets the project canvas size. Returns false if either dimension is 0 or there's no project.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_resolution(engine: *mut Engine, width: u32, height: u32) -> bool {
+    if engine.is_null() || width == 0 || height == 0 { return false; }
+    let eng = unsafe { &mut *engine };
+    if eng.project.is_none() { return false; }
+    eng.handle(Command::SetResolution(width, height));
+    true
+}
+
+/// Switches timecode display and parsing between drop-frame and non-drop. Drop-frame only has an
+/// effect at 29.97 and 59.94.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_drop_frame(engine: *mut Engine, drop_frame: bool) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.handle(Command::SetDropFrame(drop_frame));
+}
+
+/// Sets how long newly added stills last. Existing clips keep their length. Returns false for 0
+/// or if there's no project.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_default_still_duration(engine: *mut Engine, duration_ms: u64) -> bool {
+    if engine.is_null() || duration_ms == 0 { return false; }
+    let eng = unsafe { &mut *engine };
+    if eng.project.is_none() { return false; }
+    eng.handle(Command::SetDefaultStillDuration(duration_ms));
+    true
+}
+
+/// Writes the project canvas size into `width` and `height`. Returns false if there's no project.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_resolution(engine: *const Engine, width: *mut u32, height: *mut u32) -> bool {
+    if engine.is_null() || width.is_null() || height.is_null() { return false; }
+    let eng = unsafe { &*engine };
+    if let Some(ref project) = eng.project {
+        unsafe {
+            *width = project.width;
+            *height = project.height;
+        }
+        true
+    } else {
+        false
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_fps(engine: *const Engine) -> f64 {
+    if engine.is_null() { return 0.0; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().map_or(0.0, |p| p.fps as f64 / p.fps_den.max(1) as f64)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_playback_time(engine: *const Engine) -> u64 {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &*engine };
+    eng.playback_state.time_ms
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_is_playing(engine: *const Engine) -> bool {
+    if engine.is_null() { return false; }
+    let eng = unsafe { &*engine };
+    eng.playback_state.is_playing
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_current_playback_clip_info(engine: *const Engine) -> *mut PlaybackClipInfo {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+
+    if let Some((clip, time_in_clip_ms)) = eng.get_clip_for_time() {
+        let info = Box::new(PlaybackClipInfo {
+            id: CString::new(clip.id).unwrap().into_raw(),
+            url: CString::new(clip.url).unwrap().into_raw(),
+            time_in_clip_ms,
+            is_offline: clip.is_offline,
+            is_enabled: clip.enabled,
+        });
+        Box::into_raw(info)
+    } else {
+        std::ptr::null_mut()
+    }
+}
+
+/// Match frame: the clip and source time showing at timeline time `time_ms`, independent of the
+/// playhead. Null in a gap or past the end. Free with `free_playback_clip_info`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_match_frame_at(engine: *const Engine, time_ms: u64) -> *mut PlaybackClipInfo {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+
+    if let Some((clip, time_in_clip_ms)) = eng.clip_at_time(time_ms) {
+        let info = Box::new(PlaybackClipInfo {
+            id: CString::new(clip.id).unwrap().into_raw(),
+            url: CString::new(clip.url).unwrap().into_raw(),
+            time_in_clip_ms,
+            is_offline: clip.is_offline,
+            is_enabled: clip.enabled,
+        });
+        Box::into_raw(info)
+    } else {
+        std::ptr::null_mut()
+    }
+}
+
+/// Info for the audio clip under the playhead, for driving a separate audio player.
+/// Free with `free_playback_clip_info`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_audio_clip_for_time(engine: *const Engine) -> *mut PlaybackClipInfo {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+
+    if let Some((clip, time_in_clip_ms)) = eng.get_audio_clip_for_time() {
+        let info = Box::new(PlaybackClipInfo {
+            id: CString::new(clip.id).unwrap().into_raw(),
+            url: CString::new(clip.url).unwrap().into_raw(),
+            time_in_clip_ms,
+            is_offline: clip.is_offline,
+            is_enabled: clip.enabled,
+        });
+        Box::into_raw(info)
+    } else {
+        std::ptr::null_mut()
+    }
+}
+
+/// Aggregate numbers for the project info panel: clip count, total duration, distinct source
+/// urls and the longest and shortest clip. Gaps aren't counted. All zero without a project.
+/// The caller frees the string with `free_rust_string`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_project_stats_json(engine: *const Engine) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+
+    let mut clip_count = 0;
+    let mut sources = std::collections::HashSet::new();
+    let mut longest: Option<u64> = None;
+    let mut shortest: Option<u64> = None;
+    if let Some(project) = eng.project.as_ref() {
+        for clip in project.timeline.all_clips().filter(|c| !c.is_gap()) {
+            let duration = clip.timeline_duration();
+            clip_count += 1;
+            sources.insert(clip.url.as_str());
+            longest = Some(longest.map_or(duration, |d| d.max(duration)));
+            shortest = Some(shortest.map_or(duration, |d| d.min(duration)));
+        }
+    }
+    let stats = serde_json::json!({
+        "clip_count": clip_count,
+        "total_duration_ms": eng.project.as_ref().map_or(0, |p| p.timeline.total_duration()),
+        "distinct_sources": sources.len(),
+        "longest_clip_ms": longest.unwrap_or(0),
+        "shortest_clip_ms": shortest.unwrap_or(0),
+    });
+    CString::new(stats.to_string()).unwrap().into_raw()
+}
+
+/// Start of every main-track clip plus the end of the last, as a JSON array of timeline ms:
+/// `[0, d0, d0 + d1, ...]`. Served from the offset cache. The caller frees the string with
+/// `free_rust_string`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_edit_points_json(engine: *const Engine) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+    let offsets = eng.track_offsets(MAIN_TRACK);
+    CString::new(serde_json::json!(offsets).to_string()).unwrap().into_raw()
+}
+
+/// Every source url used on any track or the audio lane, once each, in order of first use. Gaps
+/// aren't sources. The caller frees the string with `free_rust_string`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_distinct_sources_json(engine: *const Engine) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+
+    let mut seen = std::collections::HashSet::new();
+    let urls: Vec<&str> = eng.project.as_ref().map_or_else(Vec::new, |p| {
+        p.timeline.all_clips().filter(|c| !c.is_gap()).map(|c| c.url.as_str()).filter(|url| seen.insert(*url)).collect()
+    });
+    CString::new(serde_json::json!(urls).to_string()).unwrap().into_raw()
+}
+
+/// Distinct urls of the main-track clips forward playback reaches within `lookahead_ms` of the
+/// current time, starting with the one under the playhead, so Swift can preload them. Gaps and
+/// disabled clips are left out. The caller frees the string with `free_rust_string`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_upcoming_sources_json(engine: *const Engine, lookahead_ms: u64) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+
+    let mut urls: Vec<&str> = Vec::new();
+    if let Some(project) = eng.project.as_ref() {
+        let time = eng.playback_state.time_ms;
+        let horizon = time.saturating_add(lookahead_ms);
+        let offsets = eng.track_offsets(MAIN_TRACK);
+        // The clip under the playhead, then each later one until a clip starts past the horizon
+        let first = offsets.partition_point(|&b| b <= time).saturating_sub(1);
+        let clips = project.timeline.clips(MAIN_TRACK);
+        for (idx, clip) in clips.iter().enumerate().skip(first) {
+            if offsets[idx] > horizon {
+                break;
+            }
+            if !clip.is_gap() && clip.enabled && !urls.contains(&clip.url.as_str()) {
+                urls.push(&clip.url);
+            }
+        }
+    }
+    CString::new(serde_json::json!(urls).to_string()).unwrap().into_raw()
+}
+
+/// How often each source url is used, as a JSON object of url to clip count across every
+/// track and the audio lane. Gaps aren't counted. The caller frees the string with
+/// `free_rust_string`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_source_usage_json(engine: *const Engine) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+
+    let mut usage: std::collections::HashMap<&str, usize> = std::collections::HashMap::new();
+    if let Some(project) = eng.project.as_ref() {
+        for clip in project.timeline.all_clips().filter(|c| !c.is_gap()) {
+            *usage.entry(clip.url.as_str()).or_default() += 1;
+        }
+    }
+    CString::new(serde_json::json!(usage).to_string()).unwrap().into_raw()
+}
+
+/// Everything the transport UI needs in one consistent read: play state, time, rate, total
+/// duration and the clip under the playhead (null fields during a gap or past the end).
+/// The caller frees the string with `free_rust_string`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_playback_state_json(engine: *const Engine) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+
+    let current = eng.get_clip_for_time();
+    let state = serde_json::json!({
+        "is_playing": eng.playback_state.is_playing,
+        "time_ms": eng.playback_state.time_ms,
+        "rate": eng.playback_state.rate,
+        "total_duration_ms": eng.project.as_ref().map_or(0, |p| p.timeline.total_duration()),
+        "clip_id": current.as_ref().map(|(clip, _)| clip.id.clone()),
+        "time_in_clip_ms": current.as_ref().map(|(_, time)| *time),
+    });
+    CString::new(state.to_string()).unwrap().into_raw()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn free_playback_clip_info(info: *mut PlaybackClipInfo) {
+    if !info.is_null() {
+        unsafe {
+            let a = Box::from_raw(info);
+            // The strings inside were created with CString::into_raw, so we must free them.
+            let _ = CString::from_raw(a.id);
+            let _ = CString::from_raw(a.url);
+        }
+    }
+}
+
+/// Both clips of the transition under the playhead, or null when no transition is active.
+/// Free with `free_transition_info`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_current_transition_info(engine: *const Engine) -> *mut TransitionInfo {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+
+    if let Some(active) = eng.get_transition_for_time() {
+        let info = Box::new(TransitionInfo {
+            from_id: CString::new(active.from.0.id).unwrap().into_raw(),
+            from_url: CString::new(active.from.0.url).unwrap().into_raw(),
+            from_time_ms: active.from.1,
+            to_id: CString::new(active.to.0.id).unwrap().into_raw(),
+            to_url: CString::new(active.to.0.url).unwrap().into_raw(),
+            to_time_ms: active.to.1,
+            progress: active.progress,
+            kind: active.kind,
+        });
+        Box::into_raw(info)
+    } else {
+        std::ptr::null_mut()
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn free_transition_info(info: *mut TransitionInfo) {
+    if !info.is_null() {
+        unsafe {
+            let a = Box::from_raw(info);
+            let _ = CString::from_raw(a.from_id);
+            let _ = CString::from_raw(a.from_url);
+            let _ = CString::from_raw(a.to_id);
+            let _ = CString::from_raw(a.to_url);
+        }
+    }
+}
+
+// Free string resources allocated by Rust
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn free_rust_string(ptr: *mut c_char) {
+    if !ptr.is_null() {
+        unsafe { let _ = CString::from_raw(ptr); }
+    }
+}
+
+/// Frees a byte buffer returned by Rust. `len` must be the length that was reported with it.
+#[no_mangle]
+pub extern "C" fn free_rust_bytes(ptr: *mut u8, len: usize) {
+    if !ptr.is_null() {
+        unsafe { let _ = Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, len)); }
+    }
+}
+
+// Test functions for reference (defined in the header file already)
+#[no_mangle]
+pub extern "C" fn add_one(x: i32) -> i32 {
+    x + 1
+}
+
+#[no_mangle]
+pub extern "C" fn multiply_by_two(x: i32) -> i32 {
+    x * 2
+}
+
+#[no_mangle]
+pub extern "C" fn divide_by_two(x: i32) -> i32 {
+    x / 2
+}
+
+// Project management FFI functions are now data-oriented, not file-oriented.
+
+/// Serializes the current project to a JSON string.
+/// The caller is responsible for freeing the returned string with `free_rust_string`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_project_as_json(engine: *mut Engine) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &mut *engine };
+
+    if let Some(ref mut project) = eng.project {
+        project.schema_version = PROJECT_SCHEMA_VERSION;
+        match serde_json::to_string_pretty(project) {
+            Ok(json_string) => CString::new(json_string).unwrap().into_raw(),
+            Err(_) => std::ptr::null_mut(),
+        }
+    } else {
+        std::ptr::null_mut()
+    }
+}
+
+/// Loads a project from a JSON string. This resets the dirty flag.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_load_project_from_json(engine: *mut Engine, json_data: *const c_char) -> bool {
+    if engine.is_null() || json_data.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    let json = unsafe { CStr::from_ptr(json_data).to_string_lossy() };
+
+    match Project::from_json(&json) {
+        Ok(project) => {
+            eng.load_project(project);
+            for warning in &eng.load_warnings {
+                println!("engine_load_project_from_json - {}", warning);
+            }
+            true
+        }
+        Err(e) => {
+            println!("engine_load_project_from_json - Load error: {}", e);
+            eng.load_warnings.clear();
+            false
+        },
+    }
+}
+
+/// Appends the main-track clips of another project's JSON to the end of the current timeline
+/// as one edit, keeping this project's settings. The clips get fresh ids, and leave their groups
+/// behind. Gaps and clips that fail `Clip::is_valid` are left out. Returns how many were
+/// imported, or -1 if the JSON doesn't parse or there's no project.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_import_project_clips(engine: *mut Engine, json_data: *const c_char) -> i64 {
+    if engine.is_null() || json_data.is_null() { return -1; }
+    let eng = unsafe { &mut *engine };
+    if eng.project.is_none() { return -1; }
+    let json = unsafe { CStr::from_ptr(json_data).to_string_lossy() };
+
+    let mut imported = match Project::from_json(&json) {
+        Ok(project) => project,
+        Err(e) => {
+            println!("engine_import_project_clips - Load error: {}", e);
+            return -1;
+        }
+    };
+    imported.timeline.validate_and_repair();
+    let mut clips = std::mem::take(&mut imported.timeline.tracks[MAIN_TRACK].clips);
+    clips.retain(Clip::is_valid);
+    for clip in &mut clips {
+        clip.id = eng.gen_id(&clip.id);
+        clip.group_id = None;
+    }
+    let added = clips.len();
+    if added > 0 {
+        eng.handle(Command::InsertClips(MAIN_TRACK, clips, usize::MAX));
+    }
+    added as i64
+}
+
+/// Serializes the current project to bincode, which is much smaller and faster than JSON for
+/// large timelines. The length is written to `out_len`; free the buffer with `free_rust_bytes`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_project_as_bincode(engine: *mut Engine, out_len: *mut usize) -> *mut u8 {
+    if engine.is_null() || out_len.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &mut *engine };
+
+    if let Some(ref mut project) = eng.project {
+        project.schema_version = PROJECT_SCHEMA_VERSION;
+        match bincode::serialize(project) {
+            Ok(bytes) => {
+                unsafe { *out_len = bytes.len(); }
+                Box::into_raw(bytes.into_boxed_slice()) as *mut u8
+            }
+            Err(_) => std::ptr::null_mut(),
+        }
+    } else {
+        std::ptr::null_mut()
+    }
+}
+
+/// Loads a project written by `engine_get_project_as_bincode`. This resets the dirty flag.
+/// Binary projects aren't migrated, so only files from this schema version load.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_load_project_from_bincode(engine: *mut Engine, data: *const u8, len: usize) -> bool {
+    if engine.is_null() || data.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    let bytes = unsafe { std::slice::from_raw_parts(data, len) };
+
+    match bincode::deserialize::<Project>(bytes) {
+        Ok(project) if project.schema_version == PROJECT_SCHEMA_VERSION => {
+            eng.load_project(project);
+            for warning in &eng.load_warnings {
+                println!("engine_load_project_from_bincode - {}", warning);
+            }
+            true
+        }
+        Ok(project) => {
+            println!("engine_load_project_from_bincode - Load error: schema version {} is not supported, expected {}", project.schema_version, PROJECT_SCHEMA_VERSION);
+            eng.load_warnings.clear();
+            false
+        }
+        Err(e) => {
+            println!("engine_load_project_from_bincode - Load error: {}", e);
+            eng.load_warnings.clear();
+            false
+        }
+    }
+}
+
+/// Exports the main track as a CMX3600 EDL. The caller frees the string with `free_rust_string`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_export_edl(engine: *const Engine) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+    match eng.project.as_ref() {
+        Some(project) => CString::new(project.to_edl()).unwrap().into_raw(),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Exports the main track as a JSON array of clips with absolute timeline positions. This is a
+/// read-only view, not the project format. The caller frees the string with `free_rust_string`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_export_flattened_json(engine: *const Engine) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+    match eng.project.as_ref() {
+        Some(project) => CString::new(project.to_flattened_json()).unwrap().into_raw(),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Compares two saved projects without a live engine. Returns the JSON produced by
+/// `Project::diff_json`, or null if either document can't be loaded. The caller frees the
+/// string with `free_rust_string`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn project_diff_json(old_json: *const c_char, new_json: *const c_char) -> *mut c_char {
+    if old_json.is_null() || new_json.is_null() { return std::ptr::null_mut(); }
+    let old_json = unsafe { CStr::from_ptr(old_json).to_string_lossy() };
+    let new_json = unsafe { CStr::from_ptr(new_json).to_string_lossy() };
+    match (Project::from_json(&old_json), Project::from_json(&new_json)) {
+        (Ok(old), Ok(new)) => CString::new(old.diff_json(&new)).unwrap().into_raw(),
+        _ => std::ptr::null_mut(),
+    }
+}
+
+/// Exports the main track as an FCPXML document. The caller frees the string with `free_rust_string`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_export_fcpxml(engine: *const Engine) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+    match eng.project.as_ref() {
+        Some(project) => CString::new(project.to_fcpxml()).unwrap().into_raw(),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Applies a JSON array of commands in order, e.g. `[{"RemoveClip":[0,2]},"Play"]`, and returns
+/// the resulting timeline as JSON. Returns null if the commands can't be parsed, in which case
+/// none of them are applied. The caller frees the string with `free_rust_string`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_apply_commands_json(engine: *mut Engine, json_data: *const c_char) -> *mut c_char {
+    if engine.is_null() || json_data.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &mut *engine };
+    let json = unsafe { CStr::from_ptr(json_data).to_string_lossy() };
+
+    match serde_json::from_str::<Vec<Command>>(&json) {
+        Ok(commands) => {
+            for cmd in commands {
+                eng.handle(cmd);
+            }
+            match serde_json::to_string(&eng.get_timeline()) {
+                Ok(json_string) => CString::new(json_string).unwrap().into_raw(),
+                Err(_) => std::ptr::null_mut(),
+            }
+        }
+        Err(e) => {
+            println!("engine_apply_commands_json - Deserialization error: {}", e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Number of repairs made to the last loaded project. See `engine_get_last_load_warning`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_last_load_warning_count(engine: *const Engine) -> usize {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &*engine };
+    eng.load_warnings.len()
+}
+
+/// A human-readable description of one load repair, or null for an invalid index.
+/// The caller frees the string with `free_rust_string`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_last_load_warning(engine: *const Engine, idx: usize) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+    match eng.load_warnings.get(idx) {
+        Some(warning) => CString::new(warning.clone()).unwrap().into_raw(),
+        None => std::ptr::null_mut(),
+    }
+}
+
+// Command log FFI functions, for reproducing bugs
+
+/// Opens a batch for programmatic edits, e.g. re-applying synced state: until
+/// `engine_end_batch`, commands change the timeline without marking the project dirty or
+/// touching its modified time. Undo, redo and autosave revisions work as usual.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_begin_batch(engine: *mut Engine) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.suppress_dirty = true;
+}
+
+/// Closes the batch opened by `engine_begin_batch`. Leaves the dirty flag as it is.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_end_batch(engine: *mut Engine) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.suppress_dirty = false;
+}
+
+/// Starts recording every handled command except ticks. Undo and redo aren't commands and
+/// aren't recorded.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_start_command_log(engine: *mut Engine) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.command_log = Some(Vec::new());
+}
+
+/// Stops recording and discards the log.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_stop_command_log(engine: *mut Engine) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.command_log = None;
+}
+
+/// The recorded commands as a JSON array in the format `engine_apply_commands_json` takes, or
+/// null when not recording. The caller frees the string with `free_rust_string`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_export_command_log(engine: *const Engine) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+    match eng.command_log.as_ref().map(serde_json::to_string) {
+        Some(Ok(json_string)) => CString::new(json_string).unwrap().into_raw(),
+        _ => std::ptr::null_mut(),
+    }
+}
+
+/// Applies an exported log. For a faithful replay the engine must be in the state recording
+/// started from, e.g. a fresh project. Returns false if the log can't be parsed.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_replay_command_log(engine: *mut Engine, json_data: *const c_char) -> bool {
+    if engine.is_null() || json_data.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    let json = unsafe { CStr::from_ptr(json_data).to_string_lossy() };
+
+    match serde_json::from_str::<Vec<Command>>(&json) {
+        Ok(commands) => {
+            for cmd in commands {
+                eng.handle(cmd);
+            }
+            true
+        }
+        Err(e) => {
+            println!("engine_replay_command_log - Deserialization error: {}", e);
+            false
+        }
+    }
+}
+
+/// Sets the current file path in the engine. Swift calls this after a successful save/open.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_set_current_file_path(engine: *mut Engine, file_path: *const c_char) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+
+    if file_path.is_null() {
+        eng.current_file_path = None;
+    } else {
+        let path = unsafe { CStr::from_ptr(file_path).to_string_lossy().into_owned() };
+        eng.current_file_path = Some(path);
+    }
+}
+
+/// Marks the current project as saved by clearing the dirty flag.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_mark_as_saved(engine: *mut Engine) {
+    if engine.is_null() { return; }
+    let eng = unsafe { &mut *engine };
+    eng.set_dirty(false);
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_new_project(engine: *mut Engine, name: *const c_char) -> bool {
+    if engine.is_null() { return false; }
+    
+    let eng = unsafe { &mut *engine };
+    let project_name = if name.is_null() {
+        "Untitled Project".to_string()
+    } else {
+        unsafe { CStr::from_ptr(name).to_string_lossy().into_owned() }
+    };
+    
+    eng.project = Some(Project::new(project_name));
+    eng.current_file_path = None;
+    eng.load_warnings.clear();
+    eng.set_dirty(true);
+    eng.revision += 1;
+    eng.playback_state = PlaybackState::default();
+    eng.clear_history();
+    eng.timeline_changed();
+    eng.emit(&EngineEvent::TimelineChanged(eng.get_timeline()));
+    true
+}
+
+/// Renames the current project, keeping its timeline. Returns false for an empty name or without
+/// a project.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_rename_project(engine: *mut Engine, name: *const c_char) -> bool {
+    if engine.is_null() || name.is_null() { return false; }
+    let eng = unsafe { &mut *engine };
+    let name = unsafe { CStr::from_ptr(name).to_string_lossy().into_owned() };
+    if name.is_empty() || eng.project.is_none() { return false; }
+    eng.handle(Command::RenameProject(name));
+    true
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_project_name(engine: *const Engine) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    
+    let eng = unsafe { &*engine };
+    if let Some(ref project) = eng.project {
+        CString::new(project.name.clone()).unwrap().into_raw()
+    } else {
+        std::ptr::null_mut()
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_current_file_path(engine: *const Engine) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    
+    let eng = unsafe { &*engine };
+    if let Some(ref path) = eng.current_file_path {
+        CString::new(path.clone()).unwrap().into_raw()
+    } else {
+        std::ptr::null_mut()
+    }
+}
+
+/// When the project was created, as an RFC 3339 string. Free with `free_rust_string`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_created_at(engine: *const Engine) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+    match eng.project.as_ref() {
+        Some(project) => CString::new(project.created_at.clone()).unwrap().into_raw(),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// When the project was last edited, as an RFC 3339 string. Free with `free_rust_string`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_modified_at(engine: *const Engine) -> *mut c_char {
+    if engine.is_null() { return std::ptr::null_mut(); }
+    let eng = unsafe { &*engine };
+    match eng.project.as_ref() {
+        Some(project) => CString::new(project.modified_at.clone()).unwrap().into_raw(),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Fingerprint of the timeline for sync: the same timeline always gives the same value, even
+/// after a save and reload, and any edit changes it. 0 without a project.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_timeline_hash(engine: *const Engine) -> u64 {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &*engine };
+    eng.project.as_ref().map_or(0, |p| p.timeline.content_hash())
+}
+
+/// Revision of the project content, which changes with every edit. Playback doesn't count.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_get_revision(engine: *const Engine) -> u64 {
+    if engine.is_null() { return 0; }
+    let eng = unsafe { &*engine };
+    eng.revision
+}
+
+/// Whether anything changed since the autosave that captured `last_saved_revision`. Cheap enough
+/// to poll on a timer.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_should_autosave(engine: *const Engine, last_saved_revision: u64) -> bool {
+    if engine.is_null() { return false; }
+    let eng = unsafe { &*engine };
+    eng.revision != last_saved_revision
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn engine_has_unsaved_changes(engine: *const Engine) -> bool {
+    if engine.is_null() { return false; }
+    
+    let eng = unsafe { &*engine };
+    eng.is_dirty
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn c_string(s: &str) -> CString {
+        CString::new(s).unwrap()
+    }
+
+    #[test]
+    fn loading_a_backwards_clip_does_not_panic() {
+        let mut engine = Engine::new();
+        let json = r#"{
+            "name": "Backwards",
+            "created_at": "2024-01-01T00:00:00Z",
+            "modified_at": "2024-01-01T00:00:00Z",
+            "timeline": { "clips": [
+                { "id": "a", "url": "file:///a.mov", "in_point": 0, "out_point": 1000 },
+                { "id": "b", "url": "file:///b.mov", "in_point": 5000, "out_point": 2000 }
+            ] }
+        }"#;
+        assert!(engine_load_project_from_json(&mut engine, c_string(json).as_ptr()));
+
+        let clips = engine.get_timeline().tracks[MAIN_TRACK].clips.clone();
+        assert_eq!((clips[1].in_point, clips[1].out_point), (2000, 5000));
+        assert_eq!(engine_get_total_duration(&engine), 4000);
+        engine.handle(Command::Seek(10_000));
+        assert_eq!(engine.playback_state.time_ms, 4000);
+        engine.handle(Command::Seek(2500));
+        assert_eq!(engine.get_clip_for_time().map(|(c, t)| (c.id, t)), Some(("b".to_string(), 3500)));
+    }
+
+
+    // An engine whose main track holds these (id, in, out) clips, in order.
+    fn engine_with_clips(clips: &[(&str, u64, u64)]) -> Engine {
+        let mut engine = Engine::new();
+        for (idx, &(id, in_point, out_point)) in clips.iter().enumerate() {
+            let clip = Clip::new(id.to_string(), format!("file:///{}.mov", id), in_point, out_point);
+            engine.handle(Command::AddClip(MAIN_TRACK, clip, idx));
+        }
+        engine
+    }
+
+    fn clip_at(engine: &mut Engine, time_ms: u64) -> Option<(String, u64)> {
+        engine.handle(Command::Seek(time_ms));
+        engine.get_clip_for_time().map(|(clip, source_ms)| (clip.id, source_ms))
+    }
+
+    #[test]
+    fn speed_maps_timeline_time_to_source_time() {
+        let mut engine = engine_with_clips(&[("slow", 0, 1000), ("fast", 0, 2000)]);
+        engine_set_clip_speed(&mut engine, 0, 0.5);
+        engine_set_clip_speed(&mut engine, 1, 2.0);
+        assert_eq!(engine_get_total_duration(&engine), 3000);
+
+        assert_eq!(clip_at(&mut engine, 1000), Some(("slow".to_string(), 500)));
+        assert_eq!(clip_at(&mut engine, 1999), Some(("slow".to_string(), 999)));
+        assert_eq!(clip_at(&mut engine, 2000), Some(("fast".to_string(), 0)));
+        assert_eq!(clip_at(&mut engine, 2500), Some(("fast".to_string(), 1000)));
+
+        engine_set_clip_speed(&mut engine, 0, 0.0);
+        engine_set_clip_speed(&mut engine, 0, -1.0);
+        assert_eq!(engine_get_clip_speed(&engine, 0), 0.5);
+    }
+
+
+    #[test]
+    fn frame_ticks_do_not_drift() {
+        for (fps, fps_den) in [(24000, 1001), (30000, 1001), (60, 1)] {
+            let mut engine = engine_with_clips(&[("long", 0, 1_000_000)]);
+            assert!(engine_set_fps(&mut engine, fps, fps_den));
+            engine_play(&mut engine);
+            for _ in 0..10_000 {
+                engine_tick_frames(&mut engine, 1);
+            }
+            assert_eq!(engine_get_playback_frame(&engine), 10_000, "{}/{}", fps, fps_den);
+            let expected_ms = (10_000u64 * 1000 * fps_den as u64).div_ceil(fps as u64);
+            assert_eq!(engine_get_playback_time(&engine), expected_ms, "{}/{}", fps, fps_den);
+        }
+    }
+
+    #[test]
+    fn project_setters_fail_without_a_project() {
+        let mut engine = Engine::new();
+        engine.project = None;
+        assert!(!engine_set_fps(&mut engine, 24, 1));
+        assert!(!engine_set_resolution(&mut engine, 1280, 720));
+        assert!(!engine_set_default_still_duration(&mut engine, 3000));
+    }
+
+
+    #[test]
+    fn cutting_one_ms_from_an_edge_changes_nothing() {
+        let mut engine = engine_with_clips(&[("a", 1000, 5000)]);
+        let before = engine.get_timeline();
+        assert!(!engine_cut_clip(&mut engine, 0, 1001));
+        assert!(!engine_cut_clip(&mut engine, 0, 4999));
+        assert_eq!(engine.get_timeline(), before);
+
+        // A 2 ms minimum still rejects a 1 ms sliver
+        engine_set_min_clip_duration(&mut engine, 2);
+        assert!(!engine_cut_clip(&mut engine, 0, 4999));
+        assert_eq!(engine.get_timeline(), before);
+        assert!(engine_cut_clip(&mut engine, 0, 4998));
+    }
+
+
+    #[test]
+    fn ripple_trims_change_the_duration_by_the_trimmed_amount() {
+        let mut engine = engine_with_clips(&[("a", 0, 3000), ("b", 1000, 4000)]);
+        assert_eq!(engine_get_total_duration(&engine), 6000);
+
+        assert!(engine_ripple_trim_start(&mut engine, 1, 1500));
+        assert_eq!(engine_get_total_duration(&engine), 5500);
+        assert!(engine_ripple_trim_end(&mut engine, 0, 2000));
+        assert_eq!(engine_get_total_duration(&engine), 4500);
+        assert!(engine_ripple_trim_start(&mut engine, 1, 0));
+        assert_eq!(engine_get_total_duration(&engine), 6000);
+
+        // Inverting trims are rejected
+        assert!(!engine_ripple_trim_start(&mut engine, 1, 4000));
+        assert!(!engine_ripple_trim_end(&mut engine, 0, 0));
+        assert_eq!(engine_get_total_duration(&engine), 6000);
+    }
+
+
+    #[test]
+    fn edit_navigation_walks_boundaries_and_stops_at_the_ends() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 2000), ("c", 0, 500)]);
+        engine_seek(&mut engine, 500);
+        assert_eq!(engine_seek_to_next_edit(&mut engine), 1000);
+        assert_eq!(engine_seek_to_next_edit(&mut engine), 3000);
+        assert_eq!(engine_seek_to_next_edit(&mut engine), 3500);
+        assert_eq!(engine_seek_to_next_edit(&mut engine), 3500);
+
+        assert_eq!(engine_seek_to_prev_edit(&mut engine), 3000);
+        engine_seek(&mut engine, 2000);
+        assert_eq!(engine_seek_to_prev_edit(&mut engine), 1000);
+        assert_eq!(engine_seek_to_prev_edit(&mut engine), 0);
+        assert_eq!(engine_seek_to_prev_edit(&mut engine), 0);
+    }
+
+
+    #[test]
+    fn looping_wraps_ticks_past_the_end() {
+        let mut engine = engine_with_clips(&[("a", 0, 3000)]);
+        engine_set_loop(&mut engine, true, 0, 0);
+        engine_seek(&mut engine, 2800);
+        engine_play(&mut engine);
+        engine_tick(&mut engine, 500);
+        assert_eq!(engine_get_playback_time(&engine), 300);
+        assert!(engine_is_playing(&engine));
+
+        engine_set_loop(&mut engine, true, 1000, 2000);
+        engine_seek(&mut engine, 1900);
+        engine_tick(&mut engine, 250);
+        assert_eq!(engine_get_playback_time(&engine), 1150);
+
+        engine_clear_loop(&mut engine);
+        engine_tick(&mut engine, 5000);
+        assert_eq!(engine_get_playback_time(&engine), 3000);
+        assert!(!engine_is_playing(&engine));
+    }
+
+
+    #[test]
+    fn double_rate_advances_twice_as_fast_and_pauses_at_the_end() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000)]);
+        engine_set_playback_rate(&mut engine, 2.0);
+        assert_eq!(engine_get_playback_rate(&engine), 2.0);
+        engine_play(&mut engine);
+        engine_tick(&mut engine, 100);
+        assert_eq!(engine_get_playback_time(&engine), 200);
+        engine_tick(&mut engine, 450);
+        assert_eq!(engine_get_playback_time(&engine), 1000);
+        assert!(!engine_is_playing(&engine));
+
+        engine_set_playback_rate(&mut engine, 100.0);
+        assert_eq!(engine_get_playback_rate(&engine), 8.0);
+    }
+
+
+    #[test]
+    fn reverse_playback_crosses_clips_and_stops_at_zero() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 1000), ("c", 0, 1000)]);
+        engine_seek(&mut engine, 2500);
+        engine_set_playback_rate(&mut engine, -1.0);
+        engine_play(&mut engine);
+        assert_eq!(engine.get_clip_for_time().map(|(c, t)| (c.id, t)), Some(("c".to_string(), 500)));
+        engine_tick(&mut engine, 700);
+        assert_eq!(engine.get_clip_for_time().map(|(c, t)| (c.id, t)), Some(("b".to_string(), 800)));
+        engine_tick(&mut engine, 1000);
+        assert_eq!(engine.get_clip_for_time().map(|(c, t)| (c.id, t)), Some(("a".to_string(), 800)));
+
+        // Overshooting the start saturates instead of underflowing
+        engine_set_playback_rate(&mut engine, -8.0);
+        engine_tick(&mut engine, 1000);
+        assert_eq!(engine_get_playback_time(&engine), 0);
+        assert!(!engine_is_playing(&engine));
+    }
+
+
+    fn transition_at(engine: &mut Engine, time_ms: u64) -> Option<(u64, u64, f32)> {
+        engine.handle(Command::Seek(time_ms));
+        engine.get_transition_for_time().map(|t| (t.from.1, t.to.1, t.progress))
+    }
+
+    #[test]
+    fn transition_overlap_ends_at_the_cut_and_leads_into_the_in_point() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 500, 1500), ("c", 0, 1000)]);
+        assert!(!engine_add_transition(&mut engine, 0, 2000, TransitionKind::CrossDissolve));
+        assert!(engine_add_transition(&mut engine, 0, 200, TransitionKind::CrossDissolve));
+
+        assert_eq!(transition_at(&mut engine, 799), None);
+        assert_eq!(transition_at(&mut engine, 800), Some((800, 300, 0.0)));
+        assert_eq!(transition_at(&mut engine, 900), Some((900, 400, 0.5)));
+        assert_eq!(transition_at(&mut engine, 999).map(|(_, to, _)| to), Some(499));
+        assert_eq!(transition_at(&mut engine, 1000), None);
+        assert_eq!(clip_at(&mut engine, 1000), Some(("b".to_string(), 500)));
+
+        // The lead-in runs at the incoming clip's speed
+        engine_set_clip_speed(&mut engine, 1, 2.0);
+        assert_eq!(transition_at(&mut engine, 900), Some((900, 300, 0.5)));
+    }
+
+    #[test]
+    fn transitions_need_a_handle_before_the_incoming_in_point() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 1000), ("c", 100, 1000)]);
+        assert!(!engine_add_transition(&mut engine, 0, 200, TransitionKind::CrossDissolve));
+        assert!(!engine_add_transition(&mut engine, 1, 200, TransitionKind::CrossDissolve));
+        assert!(engine_add_transition(&mut engine, 1, 100, TransitionKind::CrossDissolve));
+        assert_eq!(transition_at(&mut engine, 1950), Some((950, 50, 0.5)));
+
+        // Trimming the handle away disables the transition rather than freezing its lead-in
+        engine_update_clip_range(&mut engine, 2, 50, 1000);
+        assert_eq!(transition_at(&mut engine, 1950), None);
+    }
+
+
+    #[test]
+    fn fade_opacity_at_the_start_middle_and_end() {
+        let mut engine = engine_with_clips(&[("a", 10_000, 14_000)]);
+        engine_set_clip_fades(&mut engine, 0, 1000, 1000);
+        assert_eq!(engine_get_clip_opacity_at(&engine, 0, 0), 0.0);
+        assert_eq!(engine_get_clip_opacity_at(&engine, 0, 500), 0.5);
+        assert_eq!(engine_get_clip_opacity_at(&engine, 0, 1000), 1.0);
+        assert_eq!(engine_get_clip_opacity_at(&engine, 0, 2000), 1.0);
+        assert_eq!(engine_get_clip_opacity_at(&engine, 0, 3500), 0.5);
+        assert_eq!(engine_get_clip_opacity_at(&engine, 0, 4000), 0.0);
+
+        // Offsets are along the timeline span, so speed doesn't move the ramps
+        engine_set_clip_speed(&mut engine, 0, 2.0);
+        assert_eq!(engine_get_clip_opacity_at(&engine, 0, 1000), 1.0);
+        assert_eq!(engine_get_clip_opacity_at(&engine, 0, 2000), 0.0);
+
+        // Fades longer than half the clip are clamped
+        engine_set_clip_fades(&mut engine, 0, 5000, 0);
+        assert_eq!(engine_get_clip_opacity_at(&engine, 0, 500), 0.5);
+        assert_eq!(engine_get_clip_opacity_at(&engine, 0, 1000), 1.0);
+    }
+
+
+    // Small deterministic xorshift generator, so random tests are repeatable.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % n.max(1)
+        }
+    }
+
+    #[test]
+    fn offset_cache_matches_a_linear_scan_after_every_command() {
+        let mut rng = Rng(0x2545f4914f6cdd1d);
+        for _ in 0..20 {
+            let mut engine = Engine::new();
+            for step in 0..60 {
+                let count = engine.get_timeline().tracks[MAIN_TRACK].clips.len() as u64;
+                let idx = rng.below(count) as usize;
+                let cmd = match rng.below(7) {
+                    0 | 1 => {
+                        let in_point = rng.below(5000);
+                        let clip = Clip::new(format!("c{}", step), "file:///a.mov".to_string(), in_point, in_point + 1 + rng.below(5000));
+                        Command::AddClip(MAIN_TRACK, clip, rng.below(count + 1) as usize)
+                    }
+                    2 => Command::CutClip(MAIN_TRACK, idx, rng.below(10_000)),
+                    3 => Command::RemoveClip(MAIN_TRACK, idx),
+                    4 => Command::MoveClip(MAIN_TRACK, idx, rng.below(count) as usize),
+                    5 => Command::SetClipSpeed(MAIN_TRACK, idx, [0.5, 1.0, 1.5, 3.0][rng.below(4) as usize]),
+                    _ => Command::LiftClip(MAIN_TRACK, idx),
+                };
+                engine.handle(cmd);
+                if rng.below(10) == 0 {
+                    engine.undo();
+                }
+
+                let track = engine.get_timeline().tracks[MAIN_TRACK].clone();
+                assert_eq!(engine.cached_offsets(MAIN_TRACK), Some(track.boundaries().as_slice()));
+                for _ in 0..20 {
+                    let time = rng.below(track.duration() + 100);
+                    let linear = track.clip_at(time).filter(|(c, _)| !c.is_gap()).map(|(c, offset)| (c.clone(), c.source_time_at(offset)));
+                    assert_eq!(engine.clip_at_time(time), linear);
+                }
+            }
+        }
+    }
+
+
+    #[test]
+    fn duplicate_gets_a_new_id_and_the_same_fields() {
+        let mut engine = engine_with_clips(&[("a", 100, 900), ("b", 0, 500)]);
+        engine_set_clip_volume(&mut engine, 0, 0.5);
+        engine_set_clip_speed(&mut engine, 0, 2.0);
+        assert_eq!(engine_duplicate_clip(&mut engine, 0), 1);
+        assert_eq!(engine_duplicate_clip(&mut engine, 9), -1);
+
+        let clips = engine.get_timeline().tracks[MAIN_TRACK].clips.clone();
+        assert_eq!(clips.len(), 3);
+        assert_ne!(clips[1].id, clips[0].id);
+        assert_eq!((clips[1].in_point, clips[1].out_point), (100, 900));
+        assert_eq!(Clip { id: clips[0].id.clone(), ..clips[1].clone() }, clips[0]);
+        assert_eq!(clips[2].id, "b");
+    }
+
+
+    // Copies a string returned over FFI and frees it.
+    fn take_string(ptr: *mut c_char) -> String {
+        assert!(!ptr.is_null());
+        let s = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        free_rust_string(ptr);
+        s
+    }
+
+    const V1_PROJECT: &str = r#"{
+        "name": "Old",
+        "created_at": "2020-01-01T00:00:00Z",
+        "modified_at": "2020-01-02T00:00:00Z",
+        "timeline": { "clips": [
+            { "id": "a", "url": "file:///media/a.mov", "in_point": 0, "out_point": 1000 },
+            { "id": "b", "url": "file:///media/b.mov", "in_point": 500, "out_point": 1500 }
+        ] }
+    }"#;
+
+    #[test]
+    fn v1_projects_load_into_the_current_schema() {
+        let mut engine = Engine::new();
+        assert!(engine_load_project_from_json(&mut engine, c_string(V1_PROJECT).as_ptr()));
+        let project = engine.project.as_ref().unwrap();
+        assert_eq!(project.timeline.tracks.len(), 1);
+        assert_eq!(project.timeline.tracks[MAIN_TRACK].kind, TrackKind::Video);
+        let clips = &project.timeline.tracks[MAIN_TRACK].clips;
+        assert_eq!(clips.iter().map(|c| c.id.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!((clips[0].volume, clips[0].speed, clips[0].name.as_str()), (1.0, 1.0, "a.mov"));
+        assert_eq!((project.fps, project.width), (30, 1920));
+
+        let saved: serde_json::Value = serde_json::from_str(&take_string(engine_get_project_as_json(&mut engine))).unwrap();
+        assert_eq!(saved["schema_version"], PROJECT_SCHEMA_VERSION);
+        assert!(saved["timeline"].get("clips").is_none());
+    }
+
+    #[test]
+    fn projects_from_a_newer_schema_are_rejected() {
+        let mut engine = engine_with_clips(&[("keep", 0, 1000)]);
+        let newer = V1_PROJECT.replacen('{', &format!("{{ \"schema_version\": {},", PROJECT_SCHEMA_VERSION + 1), 1);
+        assert!(!engine_load_project_from_json(&mut engine, c_string(&newer).as_ptr()));
+        assert_eq!(engine.get_timeline().tracks[MAIN_TRACK].clips[0].id, "keep");
+    }
+
+
+    #[test]
+    fn bincode_round_trips_the_project() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 200, 800)]);
+        engine_set_clip_speed(&mut engine, 1, 1.5);
+        engine_add_marker(&mut engine, c_string("m").as_ptr(), 500, c_string("Beat").as_ptr(), 0xff0000ff);
+        engine.handle(Command::AddAudioClip(Clip::new("music".to_string(), "file:///music.m4a".to_string(), 0, 3000), 250));
+        engine_set_fps(&mut engine, 24000, 1001);
+
+        let mut len = 0;
+        let bytes = engine_get_project_as_bincode(&mut engine, &mut len);
+        let mut loaded = Engine::new();
+        assert!(engine_load_project_from_bincode(&mut loaded, bytes, len));
+        free_rust_bytes(bytes, len);
+
+        let as_json = |e: &Engine| serde_json::to_value(e.project.as_ref().unwrap()).unwrap();
+        assert_eq!(as_json(&loaded), as_json(&engine));
+        assert!(!loaded.is_dirty);
+    }
+
+
+    #[test]
+    fn edl_export_matches_a_known_good_list() {
+        let mut engine = engine_with_clips(&[("a", 0, 2000), ("b", 1000, 4000), ("c", 500, 1500)]);
+        engine_rename_project(&mut engine, c_string("Rough Cut").as_ptr());
+        let expected = "\
+TITLE: Rough Cut
+FCM: NON-DROP FRAME
+
+001  AX       V     C        00:00:00:00 00:00:02:00 00:00:00:00 00:00:02:00
+* FROM CLIP NAME: a.mov
+
+002  AX       V     C        00:00:01:00 00:00:04:00 00:00:02:00 00:00:05:00
+* FROM CLIP NAME: b.mov
+
+003  AX       V     C        00:00:00:15 00:00:01:15 00:00:05:00 00:00:06:00
+* FROM CLIP NAME: c.mov
+";
+        assert_eq!(take_string(engine_export_edl(&engine)), expected);
+    }
+
+
+    #[test]
+    fn fcpxml_has_one_asset_clip_per_clip() {
+        let mut engine = engine_with_clips(&[("a", 0, 2000), ("b", 1000, 4000)]);
+        engine.handle(Command::AddClip(MAIN_TRACK, Clip::new("a2".to_string(), "file:///a.mov".to_string(), 3000, 5000), 2));
+        engine_lift_clip(&mut engine, 1);
+        let xml = take_string(engine_export_fcpxml(&engine));
+        assert_eq!(xml.matches("<asset-clip ").count(), 2);
+        assert_eq!(xml.matches("<gap ").count(), 1);
+        assert_eq!(xml.matches("<asset ").count(), 1);
+        assert!(xml.contains("width=\"1920\" height=\"1080\""));
+    }
+
+
+    #[test]
+    fn poster_time_is_clamped_to_the_clip_range() {
+        let mut engine = engine_with_clips(&[("a", 1000, 3000)]);
+        assert_eq!(engine_get_clip_poster_time(&engine, 0), 1000);
+        engine_set_clip_poster_time(&mut engine, 0, 2000);
+        assert_eq!(engine_get_clip_poster_time(&engine, 0), 2000);
+        engine_set_clip_poster_time(&mut engine, 0, 500);
+        assert_eq!(engine_get_clip_poster_time(&engine, 0), 1000);
+        engine_set_clip_poster_time(&mut engine, 0, 9000);
+        assert_eq!(engine_get_clip_poster_time(&engine, 0), 3000);
+
+        // A later trim pulls the poster frame back inside the range
+        engine_update_clip_range(&mut engine, 0, 1000, 2500);
+        assert_eq!(engine_get_clip_poster_time(&engine, 0), 2500);
+    }
+
+
+    #[test]
+    fn closing_gaps_shrinks_the_duration_by_the_gap_sizes() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 2000), ("c", 0, 3000), ("d", 0, 500)]);
+        engine_lift_clip(&mut engine, 1);
+        engine_lift_clip(&mut engine, 3);
+        assert_eq!(engine_get_gap_count(&engine), 2);
+        assert_eq!(engine_get_total_duration(&engine), 6500);
+
+        engine_close_gaps(&mut engine);
+        assert_eq!(engine_get_gap_count(&engine), 0);
+        assert_eq!(engine_get_total_duration(&engine), 6500 - 2000 - 500);
+        assert_eq!(engine_get_clip_count(&engine), 2);
+    }
+
+
+    // (id, in, out) of each main-track clip.
+    fn main_clips(engine: &Engine) -> Vec<(String, u64, u64)> {
+        engine.get_timeline().tracks[MAIN_TRACK].clips.iter().map(|c| (c.id.clone(), c.in_point, c.out_point)).collect()
+    }
+
+    fn insert_at(engine: &mut Engine, id: &str, in_ms: u64, out_ms: u64, time_ms: u64) {
+        engine_insert_clip_at_time(engine, c_string(id).as_ptr(), c_string("file:///new.mov").as_ptr(), in_ms, out_ms, time_ms);
+    }
+
+    #[test]
+    fn inserting_at_a_time_splits_only_inside_a_clip() {
+        let mut engine = engine_with_clips(&[("a", 0, 2000), ("b", 0, 1000)]);
+        insert_at(&mut engine, "at_boundary", 0, 300, 2000);
+        assert_eq!(main_clips(&engine), [("a".into(), 0, 2000), ("at_boundary".into(), 0, 300), ("b".into(), 0, 1000)]);
+
+        insert_at(&mut engine, "mid", 0, 400, 500);
+        let clips = main_clips(&engine);
+        assert_eq!(clips.len(), 5);
+        assert_eq!((clips[0].1, clips[0].2), (0, 500));
+        assert_eq!(clips[1], ("mid".into(), 0, 400));
+        assert_eq!((clips[2].1, clips[2].2), (500, 2000));
+        assert_eq!(engine_get_total_duration(&engine), 3700);
+
+        insert_at(&mut engine, "past_end", 0, 100, 99_999);
+        assert_eq!(main_clips(&engine).last(), Some(&("past_end".into(), 0, 100)));
+        assert_eq!(engine_get_total_duration(&engine), 3800);
+    }
+
+
+    #[test]
+    fn overwriting_across_a_boundary_trims_both_neighbours() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 1000), ("c", 0, 1000)]);
+        engine_overwrite_clip_at_time(&mut engine, c_string("new").as_ptr(), c_string("file:///new.mov").as_ptr(), 0, 1000, 500);
+        let clips = main_clips(&engine);
+        assert_eq!(clips.iter().map(|c| (c.1, c.2)).collect::<Vec<_>>(), [(0, 500), (0, 1000), (500, 1000), (0, 1000)]);
+        assert_eq!(clips[1].0, "new");
+        assert_eq!(clips[3].0, "c");
+        assert_eq!(engine_get_total_duration(&engine), 3000);
+
+        // Covering whole clips removes them
+        engine_overwrite_clip_at_time(&mut engine, c_string("wide").as_ptr(), c_string("file:///new.mov").as_ptr(), 0, 2500, 0);
+        let clips = main_clips(&engine);
+        assert_eq!(clips.iter().map(|c| (c.1, c.2)).collect::<Vec<_>>(), [(0, 2500), (500, 1000)]);
+        assert_eq!(engine_get_total_duration(&engine), 3000);
+    }
+
+
+    fn selected_ids(engine: &Engine) -> Vec<String> {
+        (0..engine_get_selected_count(engine)).map(|i| take_string(engine_get_selected_clip_id(engine, i))).collect()
+    }
+
+    #[test]
+    fn removed_clips_drop_out_of_the_selection() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 1000), ("c", 0, 1000)]);
+        engine_select_clip(&mut engine, 0);
+        engine_toggle_select_clip(&mut engine, 2);
+        assert_eq!(selected_ids(&engine), ["a", "c"]);
+
+        engine_remove_clip(&mut engine, 0);
+        assert_eq!(selected_ids(&engine), ["c"]);
+        engine_toggle_select_clip(&mut engine, 1);
+        assert!(selected_ids(&engine).is_empty());
+    }
+
+
+    #[test]
+    fn removing_the_selection_keeps_unselected_clips() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 1000), ("c", 0, 1000)]);
+        engine_select_clip(&mut engine, 0);
+        engine_toggle_select_clip(&mut engine, 2);
+        assert_eq!(engine_remove_selected(&mut engine), 2);
+        assert_eq!(main_clips(&engine), [("b".into(), 0, 1000)]);
+        assert_eq!(engine_get_selected_count(&engine), 0);
+
+        // One edit, so one undo brings both back
+        assert!(engine_undo(&mut engine));
+        assert_eq!(engine_get_clip_count(&engine), 3);
+    }
+
+
+    #[test]
+    fn clip_ids_map_to_their_index_after_a_move() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 1000), ("c", 0, 1000)]);
+        engine_move_clip(&mut engine, 0, 2);
+        assert_eq!(engine_get_clip_index_by_id(&engine, c_string("a").as_ptr()), 2);
+        assert_eq!(engine_get_clip_index_by_id(&engine, c_string("b").as_ptr()), 0);
+        assert_eq!(engine_get_clip_index_by_id(&engine, c_string("missing").as_ptr()), -1);
+    }
+
+
+    #[test]
+    fn seeking_clamps_to_the_real_total_with_an_inverted_clip() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 2000)]);
+        // Bypass validation, as a hand-edited project might
+        engine.project.as_mut().unwrap().timeline.tracks[MAIN_TRACK].clips.insert(1, Clip::new("bad".to_string(), "file:///bad.mov".to_string(), 5000, 1000));
+        engine.timeline_changed();
+
+        assert_eq!(engine_get_total_duration(&engine), 3000);
+        assert_eq!(engine_seek_clamped(&mut engine, u64::MAX), 3000);
+        assert_eq!(engine_seek_clamped(&mut engine, 1500), 1500);
+        assert_eq!(engine.get_clip_for_time().map(|(c, t)| (c.id, t)), Some(("b".to_string(), 500)));
+    }
+
+
+    #[test]
+    fn split_at_time_cuts_every_track_under_the_time() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 1000)]);
+        let overlay = engine_add_track(&mut engine, TrackKind::Overlay) as usize;
+        engine_add_clip_to_track(&mut engine, overlay, c_string("title").as_ptr(), c_string("file:///title.png").as_ptr(), 0, 3000, 0);
+
+        engine_split_at_time(&mut engine, 1500);
+        let timeline = engine.get_timeline();
+        let ranges = |t: usize| timeline.clips(t).iter().map(|c| (c.in_point, c.out_point)).collect::<Vec<_>>();
+        assert_eq!(ranges(MAIN_TRACK), [(0, 1000), (0, 500), (500, 1000)]);
+        assert_eq!(ranges(overlay), [(0, 1500), (1500, 3000)]);
+
+        // On a boundary, or past every clip, nothing is cut
+        let before = engine.get_timeline();
+        engine_split_at_time(&mut engine, 1500);
+        engine_split_at_time(&mut engine, 10_000);
+        assert_eq!(engine.get_timeline(), before);
+    }
+
+
+    fn main_urls(engine: &Engine) -> Vec<String> {
+        engine.get_timeline().tracks[MAIN_TRACK].clips.iter().map(|c| c.url.clone()).collect()
+    }
+
+    #[test]
+    fn relinking_by_prefix_leaves_other_clips_alone() {
+        let mut engine = Engine::new();
+        for (idx, url) in ["/old/a.mov", "/other/b.mov", "/old/c.mov", "/old/d.mov", "/oldish/e.mov"].iter().enumerate() {
+            engine.handle(Command::AddClip(MAIN_TRACK, Clip::new(format!("c{}", idx), url.to_string(), 0, 1000), idx));
+        }
+        let undo_depth = engine.undo_stack.len();
+        assert_eq!(engine_relink_by_prefix(&mut engine, c_string("/old/").as_ptr(), c_string("/Volumes/New/").as_ptr()), 3);
+        assert_eq!(main_urls(&engine), ["/Volumes/New/a.mov", "/other/b.mov", "/Volumes/New/c.mov", "/Volumes/New/d.mov", "/oldish/e.mov"]);
+        assert_eq!(engine.undo_stack.len(), undo_depth + 1);
+        assert_eq!(main_clips(&engine).iter().map(|c| (c.1, c.2)).collect::<Vec<_>>(), [(0, 1000); 5]);
+
+        assert_eq!(engine_relink_by_prefix(&mut engine, c_string("/old/").as_ptr(), c_string("/x/").as_ptr()), 0);
+    }
+
+
+    #[test]
+    fn relinking_keeps_the_timing() {
+        let mut engine = engine_with_clips(&[("a", 250, 1750)]);
+        engine_set_clip_speed(&mut engine, 0, 1.5);
+        let before = engine.get_timeline().tracks[MAIN_TRACK].clips[0].clone();
+        assert!(engine_relink_clip(&mut engine, 0, c_string("file:///full-res/a.mov").as_ptr()));
+        let after = engine.get_timeline().tracks[MAIN_TRACK].clips[0].clone();
+        assert_eq!(after, Clip { url: "file:///full-res/a.mov".to_string(), ..before.clone() });
+
+        assert!(!engine_relink_clip(&mut engine, 0, c_string("").as_ptr()));
+        assert!(!engine_relink_clip(&mut engine, 5, c_string("file:///b.mov").as_ptr()));
+        assert_eq!(engine.get_timeline().tracks[MAIN_TRACK].clips[0], after);
+    }
+
+
+    #[test]
+    fn toggling_offline_state() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 1000)]);
+        engine_set_clip_offline(&mut engine, 1, true);
+        assert_eq!(engine_get_offline_clip_count(&engine), 1);
+        assert_eq!(engine_get_offline_clip_index(&engine, 0), 1);
+        assert_eq!(engine_get_offline_clip_index(&engine, 1), -1);
+
+        // Offline clips still play, flagged so the UI can show a placeholder
+        engine_seek(&mut engine, 1500);
+        let info = engine_get_current_playback_clip_info(&engine);
+        assert!(unsafe { (*info).is_offline });
+        free_playback_clip_info(info);
+
+        engine_set_clip_offline(&mut engine, 1, false);
+        assert_eq!(engine_get_offline_clip_count(&engine), 0);
+    }
+
+
+    fn json(ptr: *mut c_char) -> serde_json::Value {
+        serde_json::from_str(&take_string(ptr)).unwrap()
+    }
+
+    #[test]
+    fn project_stats_over_a_known_timeline() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 3000), ("gap", 0, 200)]);
+        engine.handle(Command::AddClip(MAIN_TRACK, Clip::new("a2".to_string(), "file:///a.mov".to_string(), 500, 1000), 3));
+        engine_lift_clip(&mut engine, 2);
+        assert_eq!(json(engine_get_project_stats_json(&engine)), serde_json::json!({
+            "clip_count": 3,
+            "total_duration_ms": 4700,
+            "distinct_sources": 2,
+            "longest_clip_ms": 3000,
+            "shortest_clip_ms": 500,
+        }));
+
+        engine.project = None;
+        assert_eq!(json(engine_get_project_stats_json(&engine)), serde_json::json!({
+            "clip_count": 0,
+            "total_duration_ms": 0,
+            "distinct_sources": 0,
+            "longest_clip_ms": 0,
+            "shortest_clip_ms": 0,
+        }));
+    }
+
+
+    #[test]
+    fn appending_to_empty_and_non_empty_timelines() {
+        let mut engine = Engine::new();
+        let url = c_string("file:///a.mov");
+        assert_eq!(engine_append_clip(&mut engine, c_string("first").as_ptr(), url.as_ptr(), 0, 1000), 0);
+        assert_eq!(engine_append_clip(&mut engine, c_string("second").as_ptr(), url.as_ptr(), 0, 500), 1);
+        assert_eq!(main_clips(&engine), [("first".into(), 0, 1000), ("second".into(), 0, 500)]);
+    }
+
+
+    #[test]
+    fn clearing_the_timeline_keeps_the_project_metadata() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 1000)]);
+        engine_add_marker(&mut engine, c_string("m").as_ptr(), 100, c_string("Beat").as_ptr(), 0);
+        engine_rename_project(&mut engine, c_string("Keeper").as_ptr());
+        let created_at = take_string(engine_get_created_at(&engine));
+        engine_seek(&mut engine, 1500);
+        engine_play(&mut engine);
+        engine_mark_as_saved(&mut engine);
+
+        engine_clear_timeline(&mut engine);
+        assert_eq!(engine_get_clip_count(&engine), 0);
+        assert_eq!(engine_get_marker_count(&engine), 0);
+        assert_eq!(take_string(engine_get_project_name(&engine)), "Keeper");
+        assert_eq!(take_string(engine_get_created_at(&engine)), created_at);
+        assert_eq!(engine_get_playback_time(&engine), 0);
+        assert!(!engine_is_playing(&engine));
+        assert!(engine_has_unsaved_changes(&engine));
+    }
+
+
+    #[test]
+    fn a_thousand_cuts_give_unique_ids() {
+        let mut engine = engine_with_clips(&[("a", 0, 1_000_000)]);
+        for i in 0..1000 {
+            assert!(engine_cut_clip(&mut engine, 0, 1_000_000 - 500 * (i + 1)));
+        }
+        let ids: std::collections::HashSet<_> = main_clips(&engine).into_iter().map(|c| c.0).collect();
+        assert_eq!(ids.len(), 1001);
+    }
+
+    #[test]
+    fn generated_ids_skip_caller_ids_in_the_same_format() {
+        let mut engine = engine_with_clips(&[("clip#2", 0, 10_000), ("clip", 0, 10_000)]);
+        assert!(engine_cut_clip(&mut engine, 1, 5000));
+        engine.handle(Command::InsertClips(MAIN_TRACK, vec![Clip::new("clip#7".to_string(), "file:///b.mov".to_string(), 0, 1000)], 0));
+        assert_eq!(engine_duplicate_clip(&mut engine, 0), 1);
+        let ids: Vec<_> = main_clips(&engine).into_iter().map(|c| c.0).collect();
+        let unique: std::collections::HashSet<_> = ids.iter().collect();
+        assert_eq!(unique.len(), ids.len(), "{:?}", ids);
+    }
+
+    #[test]
+    fn a_caller_id_at_the_counter_limit_is_not_repeated() {
+        let max_id = format!("clip#{}", u64::MAX);
+        let mut engine = engine_with_clips(&[(max_id.as_str(), 0, 10_000)]);
+        assert!(engine_cut_clip(&mut engine, 0, 5000));
+        assert!(engine_cut_clip(&mut engine, 1, 7500));
+        let ids: Vec<_> = main_clips(&engine).into_iter().map(|c| c.0).collect();
+        let unique: std::collections::HashSet<_> = ids.iter().collect();
+        assert_eq!(unique.len(), 3, "{:?}", ids);
+
+        // Loading a project with that id doesn't leave the counter stuck at the limit either
+        let saved = c_string(&take_string(engine_get_project_as_json(&mut engine)));
+        assert!(engine_load_project_from_json(&mut engine, saved.as_ptr()));
+        assert!(engine_cut_clip(&mut engine, 0, 2500));
+        assert_eq!(main_clips(&engine).len(), 4);
+    }
+
+
+    #[test]
+    fn a_clip_owns_the_time_at_its_exact_start() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 1000), ("c", 0, 1000)]);
+        engine_lift_clip(&mut engine, 1);
+        assert_eq!(engine_get_clip_index_at_time(&engine, 0), 0);
+        assert_eq!(engine_get_clip_index_at_time(&engine, 999), 0);
+        assert_eq!(engine_get_clip_index_at_time(&engine, 1000), -1);
+        assert_eq!(engine_get_clip_index_at_time(&engine, 2000), 2);
+        assert_eq!(engine_get_clip_index_at_time(&engine, 3000), -1);
+    }
+
+
+    #[test]
+    fn ripple_space_at_a_boundary_and_mid_clip() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 1000)]);
+        engine_ripple_space(&mut engine, 1000, 300);
+        let clips = engine.get_timeline().tracks[MAIN_TRACK].clips.clone();
+        assert_eq!(clips.len(), 3);
+        assert!(clips[1].is_gap());
+        assert_eq!(clips[1].timeline_duration(), 300);
+        assert_eq!(engine_get_total_duration(&engine), 2300);
+
+        engine_ripple_space(&mut engine, 500, 200);
+        let clips = engine.get_timeline().tracks[MAIN_TRACK].clips.clone();
+        assert_eq!(clips.iter().map(|c| (c.is_gap(), c.in_point, c.out_point)).collect::<Vec<_>>(),
+            [(false, 0, 500), (true, 0, 200), (false, 500, 1000), (true, 0, 300), (false, 0, 1000)]);
+        assert_eq!(engine_get_total_duration(&engine), 2500);
+
+        // Past the end there is nothing to push
+        engine_ripple_space(&mut engine, 2500, 100);
+        assert_eq!(engine_get_total_duration(&engine), 2500);
+    }
+
+
+    // Calls the boxed Rust closure passed as `user_data`.
+    extern "C" fn dirty_shim(is_dirty: bool, user_data: *mut c_void) {
+        let callback = unsafe { &mut *(user_data as *mut Box<dyn FnMut(bool)>) };
+        callback(is_dirty);
+    }
+
+    #[test]
+    fn dirty_callback_fires_once_per_transition() {
+        let seen = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let recorder = seen.clone();
+        let mut callback: Box<dyn FnMut(bool)> = Box::new(move |is_dirty| recorder.borrow_mut().push(is_dirty));
+
+        let mut engine = engine_with_clips(&[("a", 0, 1000)]);
+        engine_mark_as_saved(&mut engine);
+        engine_set_dirty_callback(&mut engine, Some(dirty_shim), &mut callback as *mut Box<dyn FnMut(bool)> as *mut c_void);
+
+        engine_set_clip_volume(&mut engine, 0, 0.5);
+        engine_set_clip_volume(&mut engine, 0, 0.25);
+        engine_seek(&mut engine, 500);
+        assert_eq!(*seen.borrow(), [true]);
+
+        engine_mark_as_saved(&mut engine);
+        engine_mark_as_saved(&mut engine);
+        assert_eq!(*seen.borrow(), [true, false]);
+
+        engine_set_dirty_callback(&mut engine, None, std::ptr::null_mut());
+    }
+
+
+    #[test]
+    fn revision_counts_edits_but_not_ticks() {
+        let mut engine = engine_with_clips(&[("a", 0, 10_000)]);
+        let saved = engine_get_revision(&engine);
+        assert!(!engine_should_autosave(&engine, saved));
+
+        engine_play(&mut engine);
+        engine_tick(&mut engine, 100);
+        engine_tick_frames(&mut engine, 3);
+        engine_seek(&mut engine, 5000);
+        assert_eq!(engine_get_revision(&engine), saved);
+
+        engine_cut_clip(&mut engine, 0, 2000);
+        assert_eq!(engine_get_revision(&engine), saved + 1);
+        assert!(engine_should_autosave(&engine, saved));
+        engine_undo(&mut engine);
+        assert_eq!(engine_get_revision(&engine), saved + 2);
+    }
+
+    #[test]
+    fn rejected_edits_leave_the_revision_alone() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000)]);
+        let saved = engine_get_revision(&engine);
+        assert_eq!(engine_add_clip(&mut engine, c_string("b").as_ptr(), c_string("").as_ptr(), 0, 1000, 0), -1);
+        engine.handle(Command::AddClip(MAIN_TRACK, Clip::new("b".to_string(), String::new(), 0, 1000), 0));
+        engine_remove_clip(&mut engine, 5);
+        assert!(!engine_rename_project(&mut engine, c_string("").as_ptr()));
+        upsert(&mut engine, "a", "file:///a.mov", 0, 1000);
+        assert_eq!(engine_get_revision(&engine), saved);
+        assert!(!engine_should_autosave(&engine, saved));
+
+        upsert(&mut engine, "a", "file:///a.mov", 0, 900);
+        assert_eq!(engine_get_revision(&engine), saved + 1);
+    }
+
+
+    fn seek_to_timecode(engine: &mut Engine, tc: &str) -> bool {
+        engine_seek_to_timecode(engine, c_string(tc).as_ptr())
+    }
+
+    #[test]
+    fn timecode_seeks_at_29_97_drop_and_non_drop() {
+        let mut engine = engine_with_clips(&[("long", 0, 3_600_000)]);
+        engine_set_fps(&mut engine, 30000, 1001);
+
+        // Non-drop counts every frame, so its labels run behind the clock
+        assert!(seek_to_timecode(&mut engine, "00:01:00:00"));
+        assert_eq!(engine_get_playback_time(&engine), 60_060);
+        assert_eq!(take_string(engine_get_current_timecode(&engine)), "00:01:00:00");
+
+        // Drop-frame skips ;00 and ;01 at the minute, so the same frame is labelled ;02
+        engine_set_drop_frame(&mut engine, true);
+        assert_eq!(take_string(engine_get_current_timecode(&engine)), "00:01:00;02");
+        assert!(!seek_to_timecode(&mut engine, "00:01:00;00"));
+        assert!(seek_to_timecode(&mut engine, "00:01:00;02"));
+        assert_eq!(engine_get_playback_time(&engine), 60_060);
+        assert!(seek_to_timecode(&mut engine, "00:10:00;00"));
+        assert_eq!(take_string(engine_get_current_timecode(&engine)), "00:10:00;00");
+
+        assert!(!seek_to_timecode(&mut engine, "00:00:00:30"));
+        assert!(!seek_to_timecode(&mut engine, "00:00:00"));
+        assert!(!seek_to_timecode(&mut engine, "aa:00:00:00"));
+    }
+
+    #[test]
+    fn huge_timecodes_are_rejected_or_clamped_without_overflow() {
+        let mut engine = engine_with_clips(&[("a", 0, 5000)]);
+        engine_seek(&mut engine, 1000);
+        assert!(!seek_to_timecode(&mut engine, "18446744073709551615:00:00:00"));
+        assert!(!seek_to_timecode(&mut engine, "99999999999999999999:00:00:00"));
+        assert_eq!(engine_get_playback_time(&engine), 1000);
+
+        // Large enough to overflow ms but not frames: lands on the end
+        assert!(seek_to_timecode(&mut engine, "100000000000000:00:00:00"));
+        assert_eq!(engine_get_playback_time(&engine), 5000);
+    }
+
+
+    #[test]
+    fn drop_frame_timecodes_match_known_values_and_round_trip() {
+        let mut project = Project::new("DF".to_string());
+        project.fps = 30000;
+        project.fps_den = 1001;
+        project.drop_frame = true;
+        for (frame, tc) in [(0, "00:00:00;00"), (1799, "00:00:59;29"), (1800, "00:01:00;02"), (3597, "00:01:59;29"),
+                            (3598, "00:02:00;02"), (17982, "00:10:00;00"), (17983, "00:10:00;01"), (107_892, "01:00:00;00")] {
+            assert_eq!(project.frame_to_timecode(frame), tc);
+            assert_eq!(project.timecode_to_frame(tc), Some(frame), "{}", tc);
+        }
+        for frame in 0..40_000 {
+            assert_eq!(project.timecode_to_frame(&project.frame_to_timecode(frame)), Some(frame));
+        }
+
+        // 59.94 drops four numbers a minute
+        project.fps = 60000;
+        assert_eq!(project.frame_to_timecode(3600), "00:01:00;04");
+        assert_eq!(project.timecode_to_frame("00:01:00;03"), None);
+
+        // Drop-frame only applies at NTSC rates
+        project.fps = 30;
+        project.fps_den = 1;
+        assert_eq!(project.frame_to_timecode(1800), "00:01:00:00");
+    }
+
+
+    #[test]
+    fn a_replayed_command_log_rebuilds_the_timeline() {
+        let mut engine = Engine::new();
+        engine_start_command_log(&mut engine);
+        for (idx, id) in ["a", "b", "c", "d", "e"].iter().enumerate() {
+            engine_append_clip(&mut engine, c_string(id).as_ptr(), c_string("file:///a.mov").as_ptr(), idx as u64 * 1000, idx as u64 * 1000 + 1000);
+        }
+        engine_cut_clip(&mut engine, 0, 500);
+        engine_set_clip_speed(&mut engine, 2, 2.0);
+        engine_play(&mut engine);
+        engine_tick(&mut engine, 100);
+
+        // Selection and engine settings aren't commands; the log must not depend on them
+        engine_select_clip(&mut engine, 1);
+        engine_toggle_select_clip(&mut engine, 3);
+        assert_eq!(engine_remove_selected(&mut engine), 2);
+        engine_select_clip(&mut engine, 0);
+        engine_toggle_select_clip(&mut engine, 1);
+        assert!(take_string(engine_group_selected(&mut engine)).starts_with("group#"));
+        engine_set_ripple_mode(&mut engine, false);
+        engine_remove_clip(&mut engine, 2);
+
+        let log = engine_export_command_log(&engine);
+        let mut replayed = Engine::new();
+        assert!(engine_replay_command_log(&mut replayed, log));
+        free_rust_string(log);
+        assert_eq!(replayed.get_timeline(), engine.get_timeline());
+        assert_eq!(engine_get_gap_count(&replayed), 1);
+    }
+
+
+    fn upsert(engine: &mut Engine, id: &str, url: &str, in_ms: u64, out_ms: u64) -> bool {
+        engine_upsert_clip(engine, c_string(id).as_ptr(), c_string(url).as_ptr(), in_ms, out_ms)
+    }
+
+    #[test]
+    fn upserting_updates_an_existing_id_and_appends_a_new_one() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000)]);
+        engine_set_clip_volume(&mut engine, 0, 0.5);
+        assert!(upsert(&mut engine, "a", "file:///a-v2.mov", 100, 2000));
+        assert!(!upsert(&mut engine, "b", "file:///b.mov", 0, 500));
+
+        let clips = engine.get_timeline().tracks[MAIN_TRACK].clips.clone();
+        assert_eq!(clips.len(), 2);
+        assert_eq!((clips[0].url.as_str(), clips[0].in_point, clips[0].out_point, clips[0].volume), ("file:///a-v2.mov", 100, 2000, 0.5));
+        assert_eq!(clips[0].name, "a-v2.mov");
+        assert_eq!((clips[1].id.as_str(), clips[1].in_point, clips[1].out_point), ("b", 0, 500));
+
+        // The same url keeps a name the user gave the clip
+        engine_set_clip_name(&mut engine, 0, c_string("Intro").as_ptr());
+        assert!(upsert(&mut engine, "a", "file:///a-v2.mov", 0, 2000));
+        assert_eq!(engine.get_timeline().tracks[MAIN_TRACK].clips[0].name, "Intro");
+
+        // Upserting the same values again is a no-op
+        let undo_depth = engine.undo_stack.len();
+        assert!(upsert(&mut engine, "b", "file:///b.mov", 0, 500));
+        assert_eq!(engine.undo_stack.len(), undo_depth);
+    }
+
+
+    #[test]
+    fn normalizing_flips_only_inverted_clips() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 1000), ("c", 200, 700)]);
+        engine.project.as_mut().unwrap().timeline.tracks[MAIN_TRACK].clips[1].in_point = 3000;
+        engine.handle(Command::AddAudioClip(Clip::new("music".to_string(), "file:///m.m4a".to_string(), 0, 1000), 0));
+        engine.project.as_mut().unwrap().timeline.audio_clips[0].clip.in_point = 4000;
+        engine_mark_as_saved(&mut engine);
+
+        assert_eq!(engine_normalize_ranges(&mut engine), 2);
+        assert_eq!(main_clips(&engine).iter().map(|c| (c.1, c.2)).collect::<Vec<_>>(), [(0, 1000), (1000, 3000), (200, 700)]);
+        let music = &engine.get_timeline().audio_clips[0].clip;
+        assert_eq!((music.in_point, music.out_point), (1000, 4000));
+        assert!(engine_has_unsaved_changes(&engine));
+
+        // Nothing left to flip: no edit, still clean after a save
+        engine_mark_as_saved(&mut engine);
+        assert_eq!(engine_normalize_ranges(&mut engine), 0);
+        assert!(!engine_has_unsaved_changes(&engine));
+    }
+
+
+    // Like `take_string`, but null comes back as None.
+    fn take_optional_string(ptr: *mut c_char) -> Option<String> {
+        (!ptr.is_null()).then(|| take_string(ptr))
+    }
+
+    #[test]
+    fn neighbouring_clip_ids_stop_at_both_ends() {
+        let engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 1000), ("c", 0, 1000)]);
+        let next = |id: &str| take_optional_string(engine_get_next_clip_id(&engine, c_string(id).as_ptr()));
+        let prev = |id: &str| take_optional_string(engine_get_prev_clip_id(&engine, c_string(id).as_ptr()));
+        assert_eq!(next("a").as_deref(), Some("b"));
+        assert_eq!(next("c"), None);
+        assert_eq!(prev("a"), None);
+        assert_eq!(prev("c").as_deref(), Some("b"));
+        assert_eq!(next("missing"), None);
+    }
+
+
+    #[test]
+    fn batched_edits_leave_the_project_clean() {
+        let mut engine = Engine::new();
+        engine_mark_as_saved(&mut engine);
+        let modified_at = take_string(engine_get_modified_at(&engine));
+
+        engine_begin_batch(&mut engine);
+        engine_append_clip(&mut engine, c_string("a").as_ptr(), c_string("file:///a.mov").as_ptr(), 0, 1000);
+        engine_set_clip_volume(&mut engine, 0, 0.5);
+        engine_end_batch(&mut engine);
+        assert!(!engine_has_unsaved_changes(&engine));
+        assert_eq!(take_string(engine_get_modified_at(&engine)), modified_at);
+        assert_eq!(engine_get_clip_count(&engine), 1);
+
+        engine_set_clip_volume(&mut engine, 0, 0.25);
+        assert!(engine_has_unsaved_changes(&engine));
+    }
+
+
+    #[test]
+    fn source_usage_counts_repeated_urls() {
+        let mut engine = Engine::new();
+        assert_eq!(json(engine_get_source_usage_json(&engine)), serde_json::json!({}));
+        for (idx, url) in ["file:///x.mov", "file:///y.mov", "file:///x.mov", "file:///x.mov"].iter().enumerate() {
+            engine.handle(Command::AddClip(MAIN_TRACK, Clip::new(format!("c{}", idx), url.to_string(), 0, 1000), idx));
+        }
+        engine_lift_clip(&mut engine, 1);
+        assert_eq!(json(engine_get_source_usage_json(&engine)), serde_json::json!({ "file:///x.mov": 3 }));
+    }
+
+
+    #[test]
+    fn consolidating_collapses_contiguous_fragments() {
+        let mut engine = Engine::new();
+        let fragments = [("x", 0, 100), ("x", 100, 200), ("x", 200, 300), ("x", 300, 450), ("y", 0, 50), ("x", 450, 500), ("x", 600, 700)];
+        for (idx, &(url, in_point, out_point)) in fragments.iter().enumerate() {
+            engine.handle(Command::AddClip(MAIN_TRACK, Clip::new(format!("c{}", idx), url.to_string(), in_point, out_point), idx));
+        }
+        assert_eq!(engine_consolidate(&mut engine), 3);
+        assert_eq!(main_clips(&engine), [("c0".into(), 0, 450), ("c4".into(), 0, 50), ("c5".into(), 450, 500), ("c6".into(), 600, 700)]);
+        assert_eq!(engine_consolidate(&mut engine), 0);
+    }
+
+
+    #[test]
+    fn the_playhead_can_follow_its_content_through_edits() {
+        let mut engine = engine_with_clips(&[("a", 1000, 2000), ("b", 1000, 2000), ("c", 1000, 2000)]);
+        engine_set_keep_playhead_on_content(&mut engine, true);
+        engine_seek(&mut engine, 1500);
+        engine_remove_clip(&mut engine, 0);
+        assert_eq!(engine_get_playback_time(&engine), 500);
+        assert_eq!(engine.get_clip_for_time().map(|(c, t)| (c.id, t)), Some(("b".to_string(), 1500)));
+
+        engine_ripple_trim_start(&mut engine, 0, 1200);
+        assert_eq!(engine_get_playback_time(&engine), 300);
+        assert_eq!(engine.get_clip_for_time().map(|(c, t)| (c.id, t)), Some(("b".to_string(), 1500)));
+
+        // Off, the playhead keeps its time and lands on whatever moved under it
+        engine_set_keep_playhead_on_content(&mut engine, false);
+        engine_seek(&mut engine, 700);
+        engine_remove_clip(&mut engine, 0);
+        assert_eq!(engine_get_playback_time(&engine), 700);
+        assert_eq!(engine.get_clip_for_time().map(|(c, t)| (c.id, t)), Some(("c".to_string(), 1700)));
+    }
+
+
+    // Event callback that appends each event type to the `Vec<i32>` passed as `user_data`.
+    extern "C" fn record_event(event_type: i32, _payload_json: *const c_char, user_data: *mut c_void) {
+        unsafe { &mut *(user_data as *mut Vec<i32>) }.push(event_type);
+    }
+
+    #[test]
+    fn stop_rewinds_while_pause_keeps_the_time() {
+        let mut engine = engine_with_clips(&[("a", 0, 5000)]);
+        engine_play(&mut engine);
+        engine_tick(&mut engine, 1200);
+        engine_pause(&mut engine);
+        assert_eq!(engine_get_playback_time(&engine), 1200);
+
+        engine_play(&mut engine);
+        let mut events: Vec<i32> = Vec::new();
+        engine_set_event_callback(&mut engine, Some(record_event), &mut events as *mut Vec<i32> as *mut c_void);
+        engine_stop(&mut engine);
+        engine_set_event_callback(&mut engine, None, std::ptr::null_mut());
+        assert_eq!(engine_get_playback_time(&engine), 0);
+        assert!(!engine_is_playing(&engine));
+        assert_eq!(events, [EngineEventType::PlaybackChanged as i32]);
+    }
+
+
+    #[test]
+    fn seeking_to_the_end_lands_on_the_total_duration() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 500, 2000)]);
+        engine_set_clip_speed(&mut engine, 1, 3.0);
+        engine_seek_to_end(&mut engine);
+        assert_eq!(engine_get_playback_time(&engine), engine_get_total_duration(&engine));
+        assert_eq!(engine_get_playback_time(&engine), 1500);
+        engine_seek_to_start(&mut engine);
+        assert_eq!(engine_get_playback_time(&engine), 0);
+
+        // Still the real total with an inverted clip in the mix
+        engine.project.as_mut().unwrap().timeline.tracks[MAIN_TRACK].clips.push(Clip::new("bad".to_string(), "file:///bad.mov".to_string(), 900, 100));
+        engine.timeline_changed();
+        engine_seek_to_end(&mut engine);
+        assert_eq!(engine_get_playback_time(&engine), 1500);
+    }
+
+
+    #[test]
+    fn disabled_clips_keep_their_place_and_report_it() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 1000)]);
+        let enabled_at = |engine: &mut Engine, time_ms| {
+            engine_seek(engine, time_ms);
+            let info = engine_get_current_playback_clip_info(engine);
+            let enabled = unsafe { (*info).is_enabled };
+            free_playback_clip_info(info);
+            enabled
+        };
+        assert!(enabled_at(&mut engine, 1500));
+
+        engine_set_clip_enabled(&mut engine, 1, false);
+        assert!(!enabled_at(&mut engine, 1500));
+        assert!(enabled_at(&mut engine, 500));
+        assert_eq!(engine_get_total_duration(&engine), 2000);
+
+        engine_set_clip_enabled(&mut engine, 1, true);
+        assert!(enabled_at(&mut engine, 1500));
+    }
+
+
+    #[test]
+    fn filtering_clips_by_label() {
+        let mut engine = engine_with_clips(&[("a", 0, 100), ("b", 0, 100), ("c", 0, 100), ("d", 0, 100)]);
+        engine_set_clip_label(&mut engine, 1, 0xff0000ff);
+        engine_set_clip_label(&mut engine, 3, 0xff0000ff);
+        engine_set_clip_label(&mut engine, 2, 0x00ff00ff);
+        assert_eq!(engine_get_clip_label(&engine, 2), 0x00ff00ff);
+
+        assert_eq!(json(engine_get_clips_with_label(&engine, 0xff0000ff)), serde_json::json!([1, 3]));
+        assert_eq!(json(engine_get_clips_with_label(&engine, 0)), serde_json::json!([0]));
+        assert_eq!(json(engine_get_clips_with_label(&engine, 0x0000ffff)), serde_json::json!([]));
+    }
+
+
+    #[test]
+    fn reversing_three_clips_inverts_the_order() {
+        let mut engine = engine_with_clips(&[("a", 50, 150), ("b", 50, 250), ("c", 50, 350)]);
+        engine.handle(Command::AddTransition(MAIN_TRACK, 0, 20, TransitionKind::CrossDissolve));
+        engine_reverse_timeline(&mut engine);
+
+        let ids: Vec<String> = main_clips(&engine).into_iter().map(|(id, _, _)| id).collect();
+        assert_eq!(ids, ["c", "b", "a"]);
+        assert_eq!(engine_get_total_duration(&engine), 600);
+        // The a→b transition now sits on the b→a cut
+        let transition = &engine.get_timeline().transitions[0];
+        assert_eq!((transition.from_clip_id.as_str(), transition.to_clip_id.as_str()), ("b", "a"));
+    }
+
+
+    #[test]
+    fn moving_a_group_keeps_its_clips_together() {
+        let mut engine = engine_with_clips(&[("a", 0, 100), ("b", 0, 100), ("c", 0, 100), ("d", 0, 100), ("e", 0, 100)]);
+        engine_select_clip(&mut engine, 1);
+        engine_toggle_select_clip(&mut engine, 3);
+        assert!(take_string(engine_group_selected(&mut engine)).starts_with("group#"));
+        let ids = |engine: &Engine| main_clips(engine).into_iter().map(|(id, _, _)| id).collect::<Vec<_>>();
+
+        engine_move_group(&mut engine, 3, 0);
+        assert_eq!(ids(&engine), ["b", "d", "a", "c", "e"]);
+        engine_move_group(&mut engine, 0, 3);
+        assert_eq!(ids(&engine), ["a", "c", "e", "b", "d"]);
+        assert_eq!(engine_get_total_duration(&engine), 500);
+    }
+
+
+    #[test]
+    fn finding_clips_by_partial_url() {
+        let mut engine = Engine::new();
+        let urls = ["file:///Vol/Trip/a.mov", "file:///vol/trip/b.mov", "file:///Other/c.mov"];
+        for (idx, url) in urls.iter().enumerate() {
+            engine.handle(Command::AddClip(MAIN_TRACK, Clip::new(format!("c{}", idx), url.to_string(), 0, 100), idx));
+        }
+        let find = |engine: &Engine, needle: &str| json(engine_find_clips_by_url(engine, c_string(needle).as_ptr()));
+        assert_eq!(find(&engine, "VOL/TRIP"), serde_json::json!([0, 1]));
+        assert_eq!(find(&engine, "c.mov"), serde_json::json!([2]));
+        assert_eq!(find(&engine, "missing"), serde_json::json!([]));
+    }
+
+
+    #[test]
+    fn one_undo_reverts_a_whole_transaction() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 1000), ("c", 0, 1000)]);
+        let before = main_clips(&engine);
+        let mut events: Vec<i32> = Vec::new();
+        engine_set_event_callback(&mut engine, Some(record_event), &mut events as *mut Vec<i32> as *mut c_void);
+
+        assert!(engine_begin_transaction(&mut engine));
+        engine.handle(Command::CutClip(MAIN_TRACK, 0, 500));
+        engine.handle(Command::MoveClip(MAIN_TRACK, 0, 3));
+        engine.handle(Command::UpdateClipRange(MAIN_TRACK, 0, 100, 400));
+        assert!(events.is_empty());
+        assert!(engine_commit_transaction(&mut engine));
+        engine_set_event_callback(&mut engine, None, std::ptr::null_mut());
+        assert_eq!(events, [EngineEventType::TimelineChanged as i32]);
+        assert_ne!(main_clips(&engine), before);
+
+        assert!(engine_undo(&mut engine));
+        assert_eq!(main_clips(&engine), before);
+        assert!(engine_redo(&mut engine));
+        assert_eq!(main_clips(&engine).len(), 4);
+    }
+
+    #[test]
+    fn rolling_back_restores_the_engine_as_it_was() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 1000)]);
+        engine_remove_clip(&mut engine, 1);
+        assert!(engine_undo(&mut engine));
+        engine.set_dirty(false);
+        let (before, revision, undo_depth) = (main_clips(&engine), engine_get_revision(&engine), engine.undo_stack.len());
+
+        assert!(engine_begin_transaction(&mut engine));
+        engine_cut_clip(&mut engine, 0, 500);
+        engine.handle(Command::SetFrameRate(24, 1));
+        // Undo and redo would reach past the start of the transaction
+        assert!(!engine_can_undo(&engine));
+        assert!(!engine_undo(&mut engine));
+        assert!(!engine_redo(&mut engine));
+        assert!(engine_rollback_transaction(&mut engine));
+
+        assert_eq!(main_clips(&engine), before);
+        assert_eq!(engine.project.as_ref().unwrap().fps, 30);
+        assert!(!engine.is_dirty);
+        assert_eq!(engine_get_revision(&engine), revision);
+        assert_eq!(engine.undo_stack.len(), undo_depth);
+        assert!(engine_redo(&mut engine));
+        assert_eq!(main_clips(&engine).len(), 1);
+    }
+
+
+    #[test]
+    fn soloing_the_middle_clip_plays_it_from_zero() {
+        let mut engine = engine_with_clips(&[("a", 0, 50), ("b", 100, 150), ("c", 200, 250)]);
+        assert!(engine_solo_clip(&mut engine, 1));
+        assert_eq!(clip_at(&mut engine, 0), Some(("b".to_string(), 100)));
+        assert_eq!(clip_at(&mut engine, 49), Some(("b".to_string(), 149)));
+        assert_eq!(clip_at(&mut engine, 60), None);
+        assert!(!engine_solo_clip(&mut engine, 3));
+
+        engine_clear_solo(&mut engine);
+        assert_eq!(clip_at(&mut engine, 0), Some(("a".to_string(), 0)));
+    }
+
+
+    #[test]
+    fn nudging_past_the_limits_clamps() {
+        let mut engine = engine_with_clips(&[("a", 100, 500)]);
+        engine.handle(Command::SetClipSourceDuration(MAIN_TRACK, 0, Some(600)));
+        let range = |engine: &Engine| main_clips(engine)[0].1..main_clips(engine)[0].2;
+
+        engine_nudge_clip_in(&mut engine, 0, -40);
+        assert_eq!(range(&engine), 60..500);
+        engine_nudge_clip_in(&mut engine, 0, -1000);
+        assert_eq!(range(&engine), 0..500);
+        engine_nudge_clip_in(&mut engine, 0, i64::MAX);
+        assert_eq!(range(&engine), 499..500);
+
+        engine_nudge_clip_in(&mut engine, 0, -399);
+        engine_nudge_clip_out(&mut engine, 0, 1000);
+        assert_eq!(range(&engine), 100..600);
+        engine_nudge_clip_out(&mut engine, 0, i64::MIN);
+        assert_eq!(range(&engine), 100..101);
+    }
+
+
+    #[test]
+    fn edit_points_match_a_manual_sum() {
+        let mut engine = engine_with_clips(&[("a", 10, 50), ("b", 10, 87), ("c", 10, 124), ("d", 10, 161)]);
+        engine_set_clip_speed(&mut engine, 1, 2.0);
+        let mut expected = vec![0];
+        for clip in engine.get_timeline().clips(MAIN_TRACK) {
+            expected.push(expected.last().unwrap() + clip.timeline_duration());
+        }
+        assert_eq!(json(engine_get_edit_points_json(&engine)), serde_json::json!(expected));
+        assert_eq!(expected.last(), Some(&engine_get_total_duration(&engine)));
+    }
+
+
+    #[test]
+    fn snapping_respects_the_tolerance() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 1000)]);
+        engine.handle(Command::AddMarker(Marker { id: "m".to_string(), time_ms: 1500, label: String::new(), color: 0 }));
+        assert_eq!(engine_snap_time(&engine, 990, 10), 1000);
+        assert_eq!(engine_snap_time(&engine, 989, 10), 989);
+        assert_eq!(engine_snap_time(&engine, 1008, 10), 1000);
+        assert_eq!(engine_snap_time(&engine, 1011, 10), 1011);
+        assert_eq!(engine_snap_time(&engine, 1495, 10), 1500);
+    }
+
+
+    #[test]
+    fn flattened_positions_are_contiguous() {
+        let mut engine = engine_with_clips(&[("a", 0, 40), ("b", 100, 141), ("c", 200, 242)]);
+        engine_set_clip_speed(&mut engine, 1, 2.0);
+
+        let flat = json(engine_export_flattened_json(&engine));
+        let entries = flat.as_array().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0], serde_json::json!({"id": "a", "url": "file:///a.mov", "source_in": 0, "source_out": 40, "timeline_start": 0, "timeline_end": 40}));
+        for pair in entries.windows(2) {
+            assert_eq!(pair[1]["timeline_start"], pair[0]["timeline_end"]);
+        }
+        assert_eq!(entries[1]["timeline_end"], 61);
+        assert_eq!(entries[2]["timeline_end"], engine_get_total_duration(&engine));
+    }
+
+
+    #[test]
+    fn a_freeze_frame_holds_one_source_time() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 100, 600)]);
+        engine.handle(Command::SetClipSourceDuration(MAIN_TRACK, 1, Some(700)));
+        engine_set_clip_freeze(&mut engine, 1, true, 300);
+        for time_ms in [1000, 1001, 1250, 1499] {
+            assert_eq!(clip_at(&mut engine, time_ms), Some(("b".to_string(), 300)));
+        }
+        assert_eq!(engine_get_total_duration(&engine), 1500);
+
+        // A frame near the end of the media is held without pushing the range past it
+        engine_set_clip_freeze(&mut engine, 1, true, 5000);
+        assert_eq!(clip_at(&mut engine, 1250), Some(("b".to_string(), 699)));
+        assert_eq!(main_clips(&engine)[1], ("b".to_string(), 100, 600));
+
+        engine_set_clip_freeze(&mut engine, 1, false, 0);
+        assert_eq!(clip_at(&mut engine, 1250), Some(("b".to_string(), 350)));
+    }
+
+
+    #[test]
+    fn clips_without_a_url_or_a_range_are_rejected() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000)]);
+        let url = c_string("file:///b.mov");
+        let empty = c_string("");
+        assert_eq!(engine_add_clip(&mut engine, c_string("b").as_ptr(), empty.as_ptr(), 0, 1000, 0), -1);
+        assert_eq!(engine_add_clip(&mut engine, c_string("b").as_ptr(), url.as_ptr(), 500, 500, 0), -1);
+        assert!(!engine_try_add_clip(&mut engine, c_string("b").as_ptr(), url.as_ptr(), 900, 100, 0));
+        assert_eq!(engine_append_clip(&mut engine, c_string("b").as_ptr(), empty.as_ptr(), 0, 1000), -1);
+        assert!(!upsert(&mut engine, "b", "file:///b.mov", 700, 200));
+
+        // The range and time-based entry points reject the same clips without splitting anything
+        engine_insert_clip_at_time(&mut engine, c_string("b").as_ptr(), empty.as_ptr(), 0, 1000, 500);
+        engine_overwrite_clip_at_time(&mut engine, c_string("b").as_ptr(), url.as_ptr(), 300, 300, 500);
+        engine_add_audio_clip(&mut engine, c_string("music").as_ptr(), empty.as_ptr(), 0, 1000, 0);
+        engine.handle(Command::InsertClips(MAIN_TRACK, vec![Clip::new("b".to_string(), String::new(), 0, 1000)], 0));
+        assert_eq!(main_clips(&engine), [("a".to_string(), 0, 1000)]);
+        assert!(engine.get_timeline().audio_clips.is_empty());
+        assert!(engine_try_add_clip(&mut engine, c_string("b").as_ptr(), url.as_ptr(), 0, 1000, 1));
+    }
+
+    #[test]
+    fn imports_skip_entries_that_would_be_rejected() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000)]);
+        let manifest = c_string(r#"[{"url": "file:///b.mov", "duration_ms": 500}, {"url": "", "duration_ms": 500}, {"url": "file:///c.mov"}]"#);
+        let mut skipped = 0;
+        assert_eq!(engine_import_clips_from_manifest(&mut engine, manifest.as_ptr(), 1, &mut skipped), 1);
+        assert_eq!(skipped, 2);
+        assert_eq!(main_urls(&engine), ["file:///a.mov", "file:///b.mov"]);
+
+        // A lifted clip leaves a gap, which isn't carried over
+        let mut other = engine_with_clips(&[("x", 0, 100), ("y", 0, 100), ("z", 0, 100)]);
+        other.handle(Command::LiftClip(MAIN_TRACK, 1));
+        let project = c_string(&take_string(engine_get_project_as_json(&mut other)));
+        assert_eq!(engine_import_project_clips(&mut engine, project.as_ptr()), 2);
+        assert_eq!(main_urls(&engine), ["file:///a.mov", "file:///b.mov", "file:///x.mov", "file:///z.mov"]);
+    }
+
+
+    #[test]
+    fn upcoming_sources_include_the_next_clip_near_a_boundary() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 1000), ("c", 0, 1000)]);
+        engine_seek(&mut engine, 900);
+        assert_eq!(json(engine_get_upcoming_sources_json(&engine, 50)), serde_json::json!(["file:///a.mov"]));
+        assert_eq!(json(engine_get_upcoming_sources_json(&engine, 200)), serde_json::json!(["file:///a.mov", "file:///b.mov"]));
+        engine_seek(&mut engine, 1000);
+        assert_eq!(json(engine_get_upcoming_sources_json(&engine, 0)), serde_json::json!(["file:///b.mov"]));
+    }
+
+
+    #[test]
+    fn removing_with_ripple_closes_the_space() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 1000), ("c", 0, 1000)]);
+        engine_remove_clip(&mut engine, 1);
+        assert_eq!(main_clips(&engine), [("a".to_string(), 0, 1000), ("c".to_string(), 0, 1000)]);
+        assert_eq!(engine_get_total_duration(&engine), 2000);
+    }
+
+    #[test]
+    fn removing_without_ripple_leaves_a_gap() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 1000), ("c", 0, 1000)]);
+        engine_set_ripple_mode(&mut engine, false);
+        engine_remove_clip(&mut engine, 1);
+        let gap = engine.get_timeline().clips(MAIN_TRACK)[1].clone();
+        assert!(gap.is_gap());
+        assert_eq!(gap.timeline_duration(), 1000);
+        assert_eq!(clip_at(&mut engine, 2500), Some(("c".to_string(), 500)));
+
+        // Trims leave the same kind of gap, so downstream clips stay put
+        engine.handle(Command::RippleTrimEnd(MAIN_TRACK, 0, 600));
+        assert_eq!(engine_get_total_duration(&engine), 3000);
+        assert_eq!(clip_at(&mut engine, 2500), Some(("c".to_string(), 500)));
+
+        // Raising an in point leaves the gap before the clip, so its remaining frames don't move
+        let c = engine.get_timeline().clips(MAIN_TRACK).len() - 1;
+        engine_nudge_clip_in(&mut engine, c, 200);
+        assert_eq!(engine_get_total_duration(&engine), 3000);
+        assert!(engine.get_timeline().clips(MAIN_TRACK)[c].is_gap());
+        assert_eq!(clip_at(&mut engine, 2100), None);
+        assert_eq!(clip_at(&mut engine, 2500), Some(("c".to_string(), 500)));
+
+        // Trimming both ends at once splits the lost time between the two sides
+        engine.handle(Command::UpdateClipRange(MAIN_TRACK, c + 1, 300, 900));
+        assert_eq!(engine_get_total_duration(&engine), 3000);
+        assert_eq!(clip_at(&mut engine, 2500), Some(("c".to_string(), 500)));
+        assert_eq!(clip_at(&mut engine, 2950), None);
+    }
+
+
+    #[test]
+    fn match_frame_across_a_multi_clip_timeline() {
+        let mut engine = engine_with_clips(&[("a", 100, 1100), ("b", 5000, 6000)]);
+        engine_ripple_space(&mut engine, 1000, 500);
+        let match_frame = |engine: &Engine, time_ms| {
+            let info = engine_match_frame_at(engine, time_ms);
+            if info.is_null() {
+                return None;
+            }
+            let found = unsafe { (CStr::from_ptr((*info).id).to_str().unwrap().to_string(), (*info).time_in_clip_ms) };
+            free_playback_clip_info(info);
+            Some(found)
+        };
+        assert_eq!(match_frame(&engine, 0), Some(("a".to_string(), 100)));
+        assert_eq!(match_frame(&engine, 999), Some(("a".to_string(), 1099)));
+        assert_eq!(match_frame(&engine, 1200), None);
+        assert_eq!(match_frame(&engine, 2250), Some(("b".to_string(), 5750)));
+        assert_eq!(match_frame(&engine, 2500), None);
+        // Querying doesn't move the playhead
+        assert_eq!(engine_get_playback_time(&engine), 0);
+    }
+
+
+    #[test]
+    fn only_contiguous_segments_of_one_source_can_join() {
+        let mut engine = Engine::new();
+        let segments = [("file:///x.mov", 0, 10), ("file:///x.mov", 10, 20), ("file:///x.mov", 25, 30), ("file:///y.mov", 30, 40)];
+        for (idx, &(url, in_point, out_point)) in segments.iter().enumerate() {
+            engine.handle(Command::AddClip(MAIN_TRACK, Clip::new(format!("c{}", idx), url.to_string(), in_point, out_point), idx));
+        }
+        assert!(engine_can_join(&engine, 0));
+        assert!(!engine_can_join(&engine, 1)); // skips 20..25
+        assert!(!engine_can_join(&engine, 2)); // different source
+        assert!(!engine_can_join(&engine, 3)); // no next clip
+    }
+
+
+    #[test]
+    fn diffing_a_known_before_and_after() {
+        let mut engine = engine_with_clips(&[("a", 0, 100), ("b", 0, 100), ("c", 0, 100), ("d", 0, 100)]);
+        let before = c_string(&take_string(engine_get_project_as_json(&mut engine)));
+        engine_remove_clip(&mut engine, 1); // a c d
+        engine_move_clip(&mut engine, 2, 0); // d a c
+        engine.handle(Command::UpdateClipRange(MAIN_TRACK, 2, 10, 50));
+        engine.handle(Command::AddClip(MAIN_TRACK, Clip::new("n".to_string(), "file:///n.mov".to_string(), 0, 10), 1)); // d n a c
+        let after = c_string(&take_string(engine_get_project_as_json(&mut engine)));
+
+        let diff = json(project_diff_json(before.as_ptr(), after.as_ptr()));
+        let ids = |key: &str| diff[key].as_array().unwrap().iter().map(|c| c["id"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+        assert_eq!(ids("added"), ["n"]);
+        assert_eq!(ids("removed"), ["b"]);
+        assert_eq!(ids("moved"), ["d", "a", "c"]);
+        // Positions count only the clips kept in both versions
+        assert_eq!(diff["moved"][0]["from"], serde_json::json!({"track": 0, "position": 2}));
+        assert_eq!(diff["moved"][0]["to"], serde_json::json!({"track": 0, "position": 0}));
+        assert_eq!(ids("modified"), ["c"]);
+        assert_eq!((&diff["modified"][0]["old"]["in_point"], &diff["modified"][0]["new"]["in_point"]), (&serde_json::json!(0), &serde_json::json!(10)));
+
+        let same = json(project_diff_json(before.as_ptr(), before.as_ptr()));
+        assert_eq!(same, serde_json::json!({"added": [], "modified": [], "moved": [], "removed": []}));
+    }
+
+
+    #[test]
+    fn timeline_hash_survives_a_reload_and_tracks_edits() {
+        let mut engine = engine_with_clips(&[("a", 0, 100), ("b", 0, 100), ("c", 0, 100)]);
+        engine_set_clip_volume(&mut engine, 1, 0.3);
+        let hash = engine_get_timeline_hash(&engine);
+        assert_eq!(engine_get_timeline_hash(&engine), hash);
+
+        let saved = c_string(&take_string(engine_get_project_as_json(&mut engine)));
+        let mut reloaded = Engine::new();
+        assert!(engine_load_project_from_json(&mut reloaded, saved.as_ptr()));
+        assert_eq!(engine_get_timeline_hash(&reloaded), hash);
+
+        engine_set_clip_volume(&mut engine, 1, 0.31);
+        assert_ne!(engine_get_timeline_hash(&engine), hash);
+        engine_undo(&mut engine);
+        engine_move_clip(&mut engine, 0, 1);
+        assert_ne!(engine_get_timeline_hash(&engine), hash);
+        engine_undo(&mut engine);
+        assert_eq!(engine_get_timeline_hash(&engine), hash);
+    }
+
+
+    #[test]
+    fn removing_the_last_clip_near_the_end_clamps_and_pauses() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000), ("b", 0, 1000), ("c", 0, 1000)]);
+        engine_seek(&mut engine, 2900);
+        engine_play(&mut engine);
+        engine_remove_clip(&mut engine, 2);
+        assert_eq!(engine_get_playback_time(&engine), 2000);
+        assert!(!engine_is_playing(&engine));
+
+        // An edit that leaves the playhead inside the timeline keeps playing
+        engine_seek(&mut engine, 1500);
+        engine_play(&mut engine);
+        engine.handle(Command::RippleTrimEnd(MAIN_TRACK, 1, 800));
+        assert_eq!(engine_get_playback_time(&engine), 1500);
+        assert!(engine_is_playing(&engine));
+    }
+
+
+    #[test]
+    fn slipping_keeps_the_duration_and_shifts_the_range() {
+        let mut engine = engine_with_clips(&[("a", 200, 700), ("b", 0, 100)]);
+        engine.handle(Command::SetClipSourceDuration(MAIN_TRACK, 0, Some(1000)));
+        let range = |engine: &Engine| main_clips(engine)[0].1..main_clips(engine)[0].2;
+
+        engine_slip_clip(&mut engine, 0, 100);
+        assert_eq!(range(&engine), 300..800);
+        // Clamped at either end of the media, never shortened
+        engine_slip_clip(&mut engine, 0, 10_000);
+        assert_eq!(range(&engine), 500..1000);
+        engine_slip_clip(&mut engine, 0, i64::MIN);
+        assert_eq!(range(&engine), 0..500);
+        assert_eq!(json(engine_get_edit_points_json(&engine)), serde_json::json!([0, 500, 600]));
+    }
+
+
+    #[test]
+    fn sliding_the_middle_clip_both_ways() {
+        let mut engine = engine_with_clips(&[("a", 100, 600), ("b", 100, 600), ("c", 100, 600)]);
+        engine.handle(Command::SetClipSourceDuration(MAIN_TRACK, 0, Some(800)));
+        let ranges = |engine: &Engine| main_clips(engine).into_iter().map(|(_, in_point, out_point)| (in_point, out_point)).collect::<Vec<_>>();
+
+        // Later: the previous clip grows, the next one gives up its head
+        engine_slide_clip(&mut engine, 1, 150);
+        assert_eq!(ranges(&engine), [(100, 750), (100, 600), (250, 600)]);
+        assert_eq!(json(engine_get_edit_points_json(&engine)), serde_json::json!([0, 650, 1150, 1500]));
+
+        // Earlier: the reverse, down to the start of the next clip's media
+        engine_slide_clip(&mut engine, 1, -250);
+        assert_eq!(ranges(&engine), [(100, 500), (100, 600), (0, 600)]);
+        assert_eq!(engine_get_total_duration(&engine), 1500);
+
+        // Past either neighbour's media it's rejected outright
+        engine_slide_clip(&mut engine, 1, -10);
+        engine_slide_clip(&mut engine, 1, 400);
+        assert_eq!(ranges(&engine), [(100, 500), (100, 600), (0, 600)]);
+    }
+
+
+    #[test]
+    fn stills_use_the_default_duration() {
+        let mut engine = Engine::new();
+        let (id, url) = (c_string("still"), c_string("file:///title.png"));
+        assert_eq!(engine_add_still(&mut engine, id.as_ptr(), url.as_ptr(), 0), 0);
+        assert!(!engine_set_default_still_duration(&mut engine, 0));
+        assert!(engine_set_default_still_duration(&mut engine, 3000));
+        assert_eq!(engine_add_still(&mut engine, id.as_ptr(), url.as_ptr(), 1), 0);
+        assert_eq!(main_clips(&engine), [("still".to_string(), 0, 5000), ("still".to_string(), 0, 3000)]);
+    }
+
+
+    #[test]
+    fn fitting_a_clip_into_half_its_length_doubles_the_speed() {
+        let mut engine = engine_with_clips(&[("a", 500, 2500)]);
+        assert!(engine_fit_clip_to_duration(&mut engine, 0, 1000));
+        let clip = engine.get_timeline().clips(MAIN_TRACK)[0].clone();
+        assert_eq!(clip.speed, 2.0);
+        assert_eq!((clip.in_point, clip.out_point), (500, 2500));
+        assert_eq!(engine_get_total_duration(&engine), 1000);
+        assert!(!engine_fit_clip_to_duration(&mut engine, 0, 0));
+    }
+
+
+    #[test]
+    fn distinct_sources_list_each_url_once() {
+        let mut engine = Engine::new();
+        assert_eq!(json(engine_get_distinct_sources_json(&engine)), serde_json::json!([]));
+        for (idx, url) in ["file:///b.mov", "file:///a.mov", "file:///b.mov", "file:///a.mov"].iter().enumerate() {
+            engine.handle(Command::AddClip(MAIN_TRACK, Clip::new(format!("c{}", idx), url.to_string(), 0, 100), idx));
+        }
+        engine.handle(Command::LiftClip(MAIN_TRACK, 1));
+        // Still listed through its second use; the gap left behind isn't a source
+        assert_eq!(json(engine_get_distinct_sources_json(&engine)), serde_json::json!(["file:///b.mov", "file:///a.mov"]));
+    }
+
+
+    #[test]
+    fn renaming_keeps_the_clips_and_creation_date() {
+        let mut engine = engine_with_clips(&[("a", 0, 100), ("b", 0, 100)]);
+        let created_at = take_string(engine_get_created_at(&engine));
+        assert!(engine_rename_project(&mut engine, c_string("Renamed").as_ptr()));
+        assert_eq!(take_string(engine_get_project_name(&engine)), "Renamed");
+        assert_eq!(take_string(engine_get_created_at(&engine)), created_at);
+        assert_eq!(main_clips(&engine).len(), 2);
+
+        assert!(!engine_rename_project(&mut engine, c_string("").as_ptr()));
+        assert_eq!(take_string(engine_get_project_name(&engine)), "Renamed");
+    }
+
+
+    #[test]
+    fn importing_three_clips_into_a_two_clip_timeline() {
+        let mut engine = engine_with_clips(&[("a", 0, 100), ("b", 0, 100)]);
+        let mut other = engine_with_clips(&[("a", 0, 50), ("x", 0, 50), ("y", 0, 50)]);
+        engine_rename_project(&mut other, c_string("Other").as_ptr());
+        let saved = c_string(&take_string(engine_get_project_as_json(&mut other)));
+        let name = take_string(engine_get_project_name(&engine));
+
+        assert_eq!(engine_import_project_clips(&mut engine, saved.as_ptr()), 3);
+        assert_eq!(main_urls(&engine), ["file:///a.mov", "file:///b.mov", "file:///a.mov", "file:///x.mov", "file:///y.mov"]);
+        assert_eq!(take_string(engine_get_project_name(&engine)), name);
+        // The imported "a" gets a fresh id rather than clashing with ours
+        let ids: std::collections::HashSet<String> = main_clips(&engine).into_iter().map(|(id, _, _)| id).collect();
+        assert_eq!(ids.len(), 5);
+
+        assert_eq!(engine_import_project_clips(&mut engine, c_string("{nope").as_ptr()), -1);
+        assert!(engine_undo(&mut engine));
+        assert_eq!(main_clips(&engine).len(), 2);
+    }
+
+
+    #[test]
+    fn null_string_arguments_are_rejected() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000)]);
+        let (id, url, null) = (c_string("b"), c_string("file:///b.mov"), std::ptr::null());
+        assert!(!engine_upsert_clip(&mut engine, null, url.as_ptr(), 0, 1000));
+        assert_eq!(engine_append_clip(&mut engine, id.as_ptr(), null, 0, 1000), -1);
+        assert_eq!(engine_add_clip_to_track(&mut engine, MAIN_TRACK, null, url.as_ptr(), 0, 1000, 0), -1);
+        engine_insert_clip_at_time(&mut engine, id.as_ptr(), null, 0, 1000, 500);
+        engine_overwrite_clip_at_time(&mut engine, null, url.as_ptr(), 0, 1000, 500);
+        engine_add_audio_clip(&mut engine, null, null, 0, 1000, 0);
+        assert_eq!(main_clips(&engine), [("a".to_string(), 0, 1000)]);
+        assert!(engine.get_timeline().audio_clips.is_empty());
+    }
+
+
+    #[test]
+    fn changing_a_setting_reports_project_changed() {
+        let mut engine = engine_with_clips(&[("a", 0, 1000)]);
+        engine.set_dirty(false);
+        let undo_depth = engine.undo_stack.len();
+        let mut events: Vec<i32> = Vec::new();
+        engine_set_event_callback(&mut engine, Some(record_event), &mut events as *mut Vec<i32> as *mut c_void);
+
+        assert!(matches!(engine.handle(Command::SetFrameRate(24, 1)), EngineEvent::ProjectChanged(ProjectSettings { fps: 24, .. })));
+        assert_eq!(events, [EngineEventType::ProjectChanged as i32]);
+        assert!(engine.is_dirty);
+
+        // Setting the same values again changes nothing, so nothing is reported or dirtied
+        engine.set_dirty(false);
+        assert!(engine_set_fps(&mut engine, 24, 1));
+        assert!(engine_rename_project(&mut engine, c_string("Untitled Project").as_ptr()));
+        engine_set_event_callback(&mut engine, None, std::ptr::null_mut());
+        assert_eq!(events.len(), 1);
+        assert!(!engine.is_dirty);
+        // Settings live outside the timeline snapshots, so none of this is an undo step
+        assert_eq!(engine.undo_stack.len(), undo_depth);
+    }
+
+
+    #[test]
+    fn a_zero_frame_rate_in_a_file_falls_back_to_the_default() {
+        let mut source = engine_with_clips(&[("a", 0, 1000)]);
+        source.project.as_mut().unwrap().fps_den = 0;
+        let saved = c_string(&take_string(engine_get_project_as_json(&mut source)));
+
+        let mut engine = Engine::new();
+        assert!(engine_load_project_from_json(&mut engine, saved.as_ptr()));
+        let project = engine.project.as_ref().unwrap();
+        assert_eq!((project.fps, project.fps_den), (30, 1));
+        assert_eq!(engine.load_warnings.len(), 1);
+
+        engine.snap_to_frames = true;
+        assert!(engine_cut_clip(&mut engine, 0, 510));
+        assert_eq!(main_clips(&engine)[0].2, 500);
+    }
+
+    #[test]
+    fn snapping_never_underflows() {
+        // There's no meaningful answer for these rates; it just mustn't panic
+        let mut project = Project::new("Broken".to_string());
+        project.fps = 0;
+        assert!(project.snap_to_frame(5000) <= 5000);
+        project.fps = 30;
+        project.fps_den = 0;
+        assert!(project.snap_to_frame(5000) <= 5000);
+    }
 }